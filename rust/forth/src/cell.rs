@@ -0,0 +1,203 @@
+use std::fmt::Display;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+use std::str::FromStr;
+
+/// A signed integer type that `Forth` can use for the values on its stack
+/// and in its memory.
+///
+/// Division and remainder are never called with a zero divisor. Types that
+/// can't overflow should return `Some` from every `checked_*` method.
+pub trait Cell:
+    Clone
+    + Ord
+    + Display
+    + FromStr
+    + From<i32>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+{
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_sub(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    fn checked_div(&self, other: &Self) -> Option<Self>;
+    fn checked_rem(&self, other: &Self) -> Option<Self>;
+    fn checked_neg(&self) -> Option<Self>;
+    fn checked_abs(&self) -> Option<Self>;
+
+    fn wrapping_add(&self, other: &Self) -> Self;
+    fn wrapping_sub(&self, other: &Self) -> Self;
+    fn wrapping_mul(&self, other: &Self) -> Self;
+    fn wrapping_div(&self, other: &Self) -> Self;
+    fn wrapping_rem(&self, other: &Self) -> Self;
+    fn wrapping_neg(&self) -> Self;
+    fn wrapping_abs(&self) -> Self;
+
+    /// The value as a memory address or cell count, if it is one.
+    fn to_usize(&self) -> Option<usize>;
+    /// A memory address or cell count as a value, if it fits.
+    fn from_usize(n: usize) -> Option<Self>;
+    /// The low eight bits, as written by `emit`.
+    fn low_byte(&self) -> u8;
+}
+
+macro_rules! impl_cell {
+    ($($t:ty),*) => {
+        $(
+            impl Cell for $t {
+                fn checked_add(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_add(*self, *other)
+                }
+
+                fn checked_sub(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_sub(*self, *other)
+                }
+
+                fn checked_mul(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_mul(*self, *other)
+                }
+
+                fn checked_div(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_div(*self, *other)
+                }
+
+                fn checked_rem(&self, other: &Self) -> Option<Self> {
+                    <$t>::checked_rem(*self, *other)
+                }
+
+                fn checked_neg(&self) -> Option<Self> {
+                    <$t>::checked_neg(*self)
+                }
+
+                fn checked_abs(&self) -> Option<Self> {
+                    <$t>::checked_abs(*self)
+                }
+
+                fn wrapping_add(&self, other: &Self) -> Self {
+                    <$t>::wrapping_add(*self, *other)
+                }
+
+                fn wrapping_sub(&self, other: &Self) -> Self {
+                    <$t>::wrapping_sub(*self, *other)
+                }
+
+                fn wrapping_mul(&self, other: &Self) -> Self {
+                    <$t>::wrapping_mul(*self, *other)
+                }
+
+                fn wrapping_div(&self, other: &Self) -> Self {
+                    <$t>::wrapping_div(*self, *other)
+                }
+
+                fn wrapping_rem(&self, other: &Self) -> Self {
+                    <$t>::wrapping_rem(*self, *other)
+                }
+
+                fn wrapping_neg(&self) -> Self {
+                    <$t>::wrapping_neg(*self)
+                }
+
+                fn wrapping_abs(&self) -> Self {
+                    <$t>::wrapping_abs(*self)
+                }
+
+                fn to_usize(&self) -> Option<usize> {
+                    usize::try_from(*self).ok()
+                }
+
+                fn from_usize(n: usize) -> Option<Self> {
+                    <$t>::try_from(n).ok()
+                }
+
+                fn low_byte(&self) -> u8 {
+                    *self as u8
+                }
+            }
+        )*
+    };
+}
+
+impl_cell!(i32, i64, i128);
+
+#[cfg(feature = "bigint")]
+mod bigint {
+    use super::Cell;
+    use num_bigint::BigInt;
+
+    /// Arbitrary-precision cells never overflow, so checked and wrapping
+    /// arithmetic are the same.
+    impl Cell for BigInt {
+        fn checked_add(&self, other: &Self) -> Option<Self> {
+            Some(self + other)
+        }
+
+        fn checked_sub(&self, other: &Self) -> Option<Self> {
+            Some(self - other)
+        }
+
+        fn checked_mul(&self, other: &Self) -> Option<Self> {
+            Some(self * other)
+        }
+
+        fn checked_div(&self, other: &Self) -> Option<Self> {
+            Some(self / other)
+        }
+
+        fn checked_rem(&self, other: &Self) -> Option<Self> {
+            Some(self % other)
+        }
+
+        fn checked_neg(&self) -> Option<Self> {
+            Some(-self)
+        }
+
+        fn checked_abs(&self) -> Option<Self> {
+            Some(self.wrapping_abs())
+        }
+
+        fn wrapping_add(&self, other: &Self) -> Self {
+            self + other
+        }
+
+        fn wrapping_sub(&self, other: &Self) -> Self {
+            self - other
+        }
+
+        fn wrapping_mul(&self, other: &Self) -> Self {
+            self * other
+        }
+
+        fn wrapping_div(&self, other: &Self) -> Self {
+            self / other
+        }
+
+        fn wrapping_rem(&self, other: &Self) -> Self {
+            self % other
+        }
+
+        fn wrapping_neg(&self) -> Self {
+            -self
+        }
+
+        fn wrapping_abs(&self) -> Self {
+            if *self < BigInt::from(0) {
+                -self
+            } else {
+                self.clone()
+            }
+        }
+
+        fn to_usize(&self) -> Option<usize> {
+            usize::try_from(self).ok()
+        }
+
+        fn from_usize(n: usize) -> Option<Self> {
+            Some(BigInt::from(n))
+        }
+
+        fn low_byte(&self) -> u8 {
+            u8::try_from(self & BigInt::from(0xff)).unwrap_or(0)
+        }
+    }
+}
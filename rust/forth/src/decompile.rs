@@ -0,0 +1,136 @@
+use crate::{Cell, Error, Forth, Instruction};
+
+impl<T: Cell> Forth<T> {
+    /// Renders the current definition of `name` back to Forth source.
+    ///
+    /// The source is rebuilt from the code as written, before any
+    /// optimization, so every call shows up. Calls to a definition that has
+    /// since been redefined are marked `( shadowed )`, and the body of a word
+    /// registered with `register` shows as `( native N )`.
+    pub fn decompile(&self, name: &str) -> Result<String, Error> {
+        let name = name.to_ascii_lowercase();
+        let idx = self
+            .words
+            .iter()
+            .rposition(|defn| defn.name == name)
+            .ok_or(Error::UnknownWord)?;
+        let defn = &self.words[idx];
+        let start = defn.source.start;
+        let body = &self.code[start..defn.source.end - 1];
+        let control = ControlWords::find(body, start);
+
+        let mut words = vec![":".to_string(), defn.name.clone()];
+        for (pc, instr) in body.iter().enumerate() {
+            control.push_labels(pc, &mut words);
+            let word = match (control.jumps[pc], instr) {
+                (Some(word), _) => word.to_string(),
+                (None, Instruction::Call(callee)) if *callee == idx => "recurse".to_string(),
+                // only a registered word's own body runs its native code
+                (None, Instruction::Native(n)) => format!("( native {} )", n),
+                (None, _) => self.decompile_instruction(instr),
+            };
+            words.push(word);
+        }
+        control.push_labels(body.len(), &mut words);
+        words.push(";".to_string());
+        Ok(words.join(" "))
+    }
+
+    pub(crate) fn decompile_instruction(&self, instr: &Instruction<T>) -> String {
+        match instr {
+            Instruction::Number(n) => n.to_string(),
+            Instruction::Call(idx) => self.call_name(*idx),
+            Instruction::Native(idx) => {
+                let registered = self.words.iter().rposition(|defn| {
+                    let body = &self.code[defn.body.clone()];
+                    matches!(body, [Instruction::Native(n), Instruction::Return] if n == idx)
+                });
+                match registered {
+                    Some(word) => self.call_name(word),
+                    None => format!("( native {} )", idx),
+                }
+            }
+            Instruction::Return => "exit".to_string(),
+            Instruction::Branch(_) => "else".to_string(),
+            Instruction::BranchIfZero(_) => "if".to_string(),
+            Instruction::Do => "do".to_string(),
+            Instruction::Loop(_) => "loop".to_string(),
+            Instruction::LoopIndex => "i".to_string(),
+            Instruction::OuterLoopIndex => "j".to_string(),
+            Instruction::Print(text) => format!(".\" {}\"", text),
+            _ => instr.builtin_name().unwrap_or_default().to_string(),
+        }
+    }
+
+    /// The name of definition `idx`, marked if a later definition shadows it.
+    fn call_name(&self, idx: usize) -> String {
+        let name = &self.words[idx].name;
+        if self.words[idx + 1..].iter().any(|defn| &defn.name == name) {
+            format!("{} ( shadowed )", name)
+        } else {
+            name.clone()
+        }
+    }
+}
+
+/// The control words that the jumps in a compiled body came from.
+struct ControlWords {
+    /// The word each jump instruction was compiled from.
+    jumps: Vec<Option<&'static str>>,
+    /// How many `then`s and `begin`s come before each instruction.
+    thens: Vec<usize>,
+    begins: Vec<usize>,
+}
+
+impl ControlWords {
+    fn find<T>(body: &[Instruction<T>], start: usize) -> Self {
+        let mut found = ControlWords {
+            jumps: vec![None; body.len()],
+            thens: vec![0; body.len() + 1],
+            begins: vec![0; body.len() + 1],
+        };
+
+        // innermost structures come last, and claim a shared `else` first
+        for pc in (0..body.len()).rev() {
+            let target = match body[pc] {
+                Instruction::BranchIfZero(target) => target - start,
+                _ => continue,
+            };
+            if target <= pc {
+                found.jumps[pc] = Some("until");
+                found.begins[target] += 1;
+                continue;
+            }
+
+            let jump = target - 1;
+            match body[jump] {
+                Instruction::Branch(to) if jump > pc && found.jumps[jump].is_none() => {
+                    let to = to - start;
+                    if to <= pc {
+                        found.jumps[pc] = Some("while");
+                        found.jumps[jump] = Some("repeat");
+                        found.begins[to] += 1;
+                    } else if to >= target {
+                        found.jumps[pc] = Some("if");
+                        found.jumps[jump] = Some("else");
+                        found.thens[to] += 1;
+                    } else {
+                        found.jumps[pc] = Some("if");
+                        found.thens[target] += 1;
+                    }
+                }
+                _ => {
+                    found.jumps[pc] = Some("if");
+                    found.thens[target] += 1;
+                }
+            }
+        }
+        found
+    }
+
+    /// Adds the words that close or open a structure at `pc`.
+    fn push_labels(&self, pc: usize, words: &mut Vec<String>) {
+        words.extend((0..self.thens[pc]).map(|_| "then".to_string()));
+        words.extend((0..self.begins[pc]).map(|_| "begin".to_string()));
+    }
+}
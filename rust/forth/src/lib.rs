@@ -1,50 +1,476 @@
+use std::fmt;
+use std::io::Write;
+use std::ops::Range;
+use std::rc::Rc;
+
+mod cell;
+mod decompile;
+mod repl;
+mod session;
+mod trace;
+
+pub use cell::Cell;
+pub use repl::repl;
+pub use trace::{print_trace, Step};
+
+/// The cell type used by `Forth::new`.
 pub type Value = i32;
 
 pub type ForthResult = Result<(), Error>;
 
+/// A word implemented in Rust, registered with `Forth::register`.
+pub type NativeWord<T = Value> = Box<dyn FnMut(&mut Vec<T>) -> ForthResult>;
+/// A hook shown each instruction before it runs.
+pub type Tracer<T = Value> = Box<dyn FnMut(&Step<T>) -> ForthResult>;
+
 #[derive(Debug, PartialEq)]
 pub enum Error {
     DivisionByZero,
     StackUnderflow,
     UnknownWord,
     InvalidWord,
+    Overflow,
+    StepLimitExceeded,
+    StackOverflow,
+    CallDepthExceeded,
+    TooManyDefinitions,
+    Output,
+    InvalidAddress,
+    OutOfMemory,
+    InvalidSession,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Error::DivisionByZero => "division by zero",
+            Error::StackUnderflow => "stack underflow",
+            Error::UnknownWord => "unknown word",
+            Error::InvalidWord => "invalid word",
+            Error::Overflow => "arithmetic overflow",
+            Error::StepLimitExceeded => "step limit exceeded",
+            Error::StackOverflow => "stack overflow",
+            Error::CallDepthExceeded => "call depth exceeded",
+            Error::TooManyDefinitions => "too many definitions",
+            Error::Output => "failed to write output",
+            Error::InvalidAddress => "invalid memory address",
+            Error::OutOfMemory => "out of memory",
+            Error::InvalidSession => "invalid saved session",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// An `Error` along with where in the input it happened.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub error: Error,
+    /// The last word read from the input before the error occurred.
+    pub word: String,
+    /// Byte offset of `word` in the input.
+    pub offset: usize,
+    /// Line of `word` in the input, starting from 1.
+    pub line: usize,
+    /// Names of the user-defined words being executed, outermost first.
+    pub call_stack: Vec<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at `{}` (line {}, byte {})",
+            self.error, self.word, self.line, self.offset
+        )?;
+        if !self.call_stack.is_empty() {
+            write!(f, " in {}", self.call_stack.join(" > "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
 }
-pub struct Forth {
-    stack: Vec<Value>,
+
+impl From<Diagnostic> for Error {
+    fn from(diagnostic: Diagnostic) -> Self {
+        diagnostic.error
+    }
+}
+
+/// Bounds on the resources a program may consume, so that untrusted input
+/// fails with an error rather than running forever or exhausting memory.
+///
+/// The default places no bounds at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Instructions executed by a single call to `Forth::eval`.
+    pub max_steps: usize,
+    /// Values held on the data stack, and separately on the return stack.
+    pub max_stack_depth: usize,
+    /// Nested calls to user-defined words.
+    pub max_call_depth: usize,
+    /// Definitions held in the dictionary, including shadowed ones.
+    pub max_definitions: usize,
+    /// Memory cells reserved by `variable` and `allot`.
+    pub max_cells: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_steps: usize::MAX,
+            max_stack_depth: usize::MAX,
+            max_call_depth: usize::MAX,
+            max_definitions: usize::MAX,
+            max_cells: usize::MAX,
+        }
+    }
+}
+
+/// How arithmetic behaves when a result doesn't fit in a cell.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Arithmetic {
+    /// Fail with `Error::Overflow`.
+    #[default]
+    Checked,
+    /// Wrap around using two's complement.
+    Wrapping,
+}
+
+impl Arithmetic {
+    fn result<T>(
+        self,
+        checked: impl FnOnce() -> Option<T>,
+        wrapped: impl FnOnce() -> T,
+    ) -> Result<T, Error> {
+        match self {
+            Arithmetic::Checked => checked().ok_or(Error::Overflow),
+            Arithmetic::Wrapping => Ok(wrapped()),
+        }
+    }
+
+    fn add<T: Cell>(self, x1: &T, x2: &T) -> Result<T, Error> {
+        self.result(|| x1.checked_add(x2), || x1.wrapping_add(x2))
+    }
+
+    fn sub<T: Cell>(self, x1: &T, x2: &T) -> Result<T, Error> {
+        self.result(|| x1.checked_sub(x2), || x1.wrapping_sub(x2))
+    }
+
+    fn mul<T: Cell>(self, x1: &T, x2: &T) -> Result<T, Error> {
+        self.result(|| x1.checked_mul(x2), || x1.wrapping_mul(x2))
+    }
+
+    fn div<T: Cell>(self, x1: &T, x2: &T) -> Result<T, Error> {
+        if *x2 == T::from(0) {
+            return Err(Error::DivisionByZero);
+        }
+        self.result(|| x1.checked_div(x2), || x1.wrapping_div(x2))
+    }
+
+    fn rem<T: Cell>(self, x1: &T, x2: &T) -> Result<T, Error> {
+        if *x2 == T::from(0) {
+            return Err(Error::DivisionByZero);
+        }
+        self.result(|| x1.checked_rem(x2), || x1.wrapping_rem(x2))
+    }
+
+    fn neg<T: Cell>(self, x: &T) -> Result<T, Error> {
+        self.result(|| x.checked_neg(), || x.wrapping_neg())
+    }
+
+    fn abs<T: Cell>(self, x: &T) -> Result<T, Error> {
+        self.result(|| x.checked_abs(), || x.wrapping_abs())
+    }
+}
+
+/// How definitions are compiled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Optimization {
+    /// Compile each word as written, so that every call shows up in call
+    /// stacks and traces, and counts towards `Limits::max_call_depth`.
+    #[default]
+    Off,
+    /// Inline small definitions into the words that call them, and fold
+    /// arithmetic on constants. Inlined calls no longer show up in call
+    /// stacks or traces, or count towards `Limits::max_call_depth`.
+    Full,
+}
+
+/// Most instructions a definition may have and still be inlined into the
+/// words that call it.
+const INLINE_LIMIT: usize = 3;
+
+/// A Forth interpreter whose stack and memory hold cells of type `T`.
+///
+/// `Forth::new` uses `i32` cells; other `Cell` types are available through
+/// `Default`, as in `Forth::<i64>::default()`.
+pub struct Forth<T = Value> {
+    stack: Vec<T>,
+    /// Compiled code for every definition, one after another. The code of a
+    /// statement run outside a definition is appended temporarily.
+    code: Vec<Instruction<T>>,
     words: Vec<Definition>,
+    natives: Vec<NativeWord<T>>,
+    memory: Vec<T>,
+    loops: Vec<LoopFrame<T>>,
+    arithmetic: Arithmetic,
+    optimization: Optimization,
+    limits: Limits,
+    steps: usize,
+    returns: Vec<Frame>,
+    /// Values moved off the data stack by `>r`.
+    return_values: Vec<T>,
+    output: Output,
+    tracer: Option<Tracer<T>>,
+}
+
+/// Splits the input into words, remembering where the last one handed out
+/// started so that errors can point at it. Comments are skipped, and parsing
+/// words such as `."` can take raw text straight from the input.
+struct Words<'a> {
+    input: &'a str,
+    /// Where reading carries on from.
+    pos: usize,
+    last: &'a str,
+    /// Byte offset of `last` in the input.
+    start: usize,
+}
+
+impl<'a> Words<'a> {
+    fn new(input: &'a str) -> Self {
+        Words {
+            input,
+            pos: 0,
+            last: &input[..0],
+            start: 0,
+        }
+    }
+
+    /// Byte offset of the last word in the input.
+    fn offset(&self) -> usize {
+        self.start
+    }
+
+    /// Takes the raw text up to `delimiter`, after the space that ends the
+    /// parsing word, and steps past the delimiter. If there is no delimiter,
+    /// the rest of the input is consumed and `None` returned.
+    fn parse(&mut self, delimiter: char) -> Option<&'a str> {
+        let rest = &self.input[self.pos..];
+        let rest = rest
+            .strip_prefix(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest);
+        let from = self.input.len() - rest.len();
+        match rest.find(delimiter) {
+            Some(len) => {
+                self.pos = from + len + delimiter.len_utf8();
+                Some(&rest[..len])
+            }
+            None => {
+                self.pos = self.input.len();
+                None
+            }
+        }
+    }
+
+    /// Skips to the end of the current line.
+    fn skip_line(&mut self) {
+        self.pos = match self.input[self.pos..].find('\n') {
+            Some(len) => self.pos + len,
+            None => self.input.len(),
+        };
+    }
+
+    fn next_token(&mut self) -> Option<&'a str> {
+        let rest = &self.input[self.pos..];
+        let start = self.pos + rest.find(|c: char| !c.is_ascii_whitespace())?;
+        self.point_at(start);
+        self.pos = start + self.last.len();
+        Some(self.last)
+    }
+
+    /// Makes the word at byte offset `start` the last word, so that errors
+    /// point at it. Reading carries on from where it was.
+    fn point_at(&mut self, start: usize) {
+        let len = self.input[start..]
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(self.input.len() - start);
+        self.start = start;
+        self.last = &self.input[start..start + len];
+    }
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = &'a str;
+
+    /// The next word that isn't part of a `( comment )` or `\ line comment`.
+    /// A comment left open runs to the end of the input.
+    fn next(&mut self) -> Option<&'a str> {
+        loop {
+            match self.next_token()? {
+                "(" => {
+                    self.parse(')');
+                }
+                "\\" => self.skip_line(),
+                word => return Some(word),
+            }
+        }
+    }
+}
+
+/// Where output words such as `.` and `emit` write to.
+enum Output {
+    Buffer(Vec<u8>),
+    Sink(Box<dyn Write>),
 }
 
 #[derive(Clone)]
-pub enum Instruction {
+pub enum Instruction<T = Value> {
     Add,
     Sub,
     Mul,
     Div,
+    Mod,
+    DivMod,
+    Negate,
+    Abs,
+    Min,
+    Max,
+    Eq,
+    Lt,
+    Gt,
+    ZeroEq,
+    And,
+    Or,
+    Xor,
+    Invert,
     Dup,
     Swap,
     Drop,
     Over,
-    Number(Value),
+    ToReturn,
+    FromReturn,
+    FetchReturn,
+    Store,
+    Fetch,
+    AddStore,
+    Allot,
+    Number(T),
     Call(usize),
+    Return,
+    Native(usize),
+    Branch(usize),
+    BranchIfZero(usize),
+    Do,
+    Loop(usize),
+    LoopIndex,
+    OuterLoopIndex,
+    Dot,
+    Emit,
+    Type,
+    Cr,
+    PrintStack,
+    Print(Rc<str>),
 }
 
 pub struct Definition {
     name: String,
-    body: Vec<Instruction>,
+    /// Where the definition's code lives in `Forth::code`, ending with a
+    /// `Return`.
+    body: Range<usize>,
+    /// Where the code as written lives, before any optimization. The same
+    /// as `body` unless the definition was optimized.
+    source: Range<usize>,
+}
+
+/// A return stack entry for a running call to a user-defined word.
+struct Frame {
+    word: usize,
+    /// Where to carry on from in the caller.
+    ret: usize,
+    /// How many loops the caller had running.
+    loops: usize,
+    /// How many values the caller had on the return stack.
+    values: usize,
+}
+
+/// The index and limit of a running `do ... loop`.
+struct LoopFrame<T> {
+    index: T,
+    limit: T,
 }
 
-fn parse_builtin(word: &str) -> Result<Instruction, Error> {
+/// An open control structure awaiting its closing word, holding the
+/// code offsets that still need to be patched or jumped back to.
+enum Control {
+    If(usize),
+    Else(usize),
+    Do(usize),
+    Begin(usize),
+    While { begin: usize, cond: usize },
+}
+
+/// A body being compiled onto the end of `Forth::code`.
+struct Compiling {
+    control: Vec<Control>,
+    /// The index the definition being compiled will have in `Forth::words`,
+    /// or `None` when compiling a statement.
+    defining: Option<usize>,
+}
+
+impl Compiling {
+    fn new(defining: Option<usize>) -> Self {
+        Compiling {
+            control: vec![],
+            defining,
+        }
+    }
+}
+
+fn parse_builtin<T: Cell>(word: &str) -> Result<Instruction<T>, Error> {
     match word {
         "+" => Ok(Instruction::Add),
         "-" => Ok(Instruction::Sub),
         "*" => Ok(Instruction::Mul),
         "/" => Ok(Instruction::Div),
+        "mod" => Ok(Instruction::Mod),
+        "/mod" => Ok(Instruction::DivMod),
+        "negate" => Ok(Instruction::Negate),
+        "abs" => Ok(Instruction::Abs),
+        "min" => Ok(Instruction::Min),
+        "max" => Ok(Instruction::Max),
+        "=" => Ok(Instruction::Eq),
+        "<" => Ok(Instruction::Lt),
+        ">" => Ok(Instruction::Gt),
+        "0=" => Ok(Instruction::ZeroEq),
+        "and" => Ok(Instruction::And),
+        "or" => Ok(Instruction::Or),
+        "xor" => Ok(Instruction::Xor),
+        "invert" => Ok(Instruction::Invert),
         "dup" => Ok(Instruction::Dup),
         "swap" => Ok(Instruction::Swap),
         "drop" => Ok(Instruction::Drop),
         "over" => Ok(Instruction::Over),
+        ">r" => Ok(Instruction::ToReturn),
+        "r>" => Ok(Instruction::FromReturn),
+        "r@" => Ok(Instruction::FetchReturn),
+        "!" => Ok(Instruction::Store),
+        "@" => Ok(Instruction::Fetch),
+        "+!" => Ok(Instruction::AddStore),
+        "allot" => Ok(Instruction::Allot),
+        "." => Ok(Instruction::Dot),
+        "emit" => Ok(Instruction::Emit),
+        "type" => Ok(Instruction::Type),
+        "cr" => Ok(Instruction::Cr),
+        ".s" => Ok(Instruction::PrintStack),
         _ => {
-            if let Ok(num) = word.parse::<Value>() {
+            if let Ok(num) = word.parse::<T>() {
                 Ok(Instruction::Number(num))
             } else {
                 Err(Error::UnknownWord)
@@ -53,97 +479,778 @@ fn parse_builtin(word: &str) -> Result<Instruction, Error> {
     }
 }
 
-impl Default for Forth {
-    fn default() -> Self {
-        Self::new()
+impl<T> Instruction<T> {
+    /// The built-in word that compiles to this instruction, if there is one.
+    fn builtin_name(&self) -> Option<&'static str> {
+        let name = match self {
+            Instruction::Add => "+",
+            Instruction::Sub => "-",
+            Instruction::Mul => "*",
+            Instruction::Div => "/",
+            Instruction::Mod => "mod",
+            Instruction::DivMod => "/mod",
+            Instruction::Negate => "negate",
+            Instruction::Abs => "abs",
+            Instruction::Min => "min",
+            Instruction::Max => "max",
+            Instruction::Eq => "=",
+            Instruction::Lt => "<",
+            Instruction::Gt => ">",
+            Instruction::ZeroEq => "0=",
+            Instruction::And => "and",
+            Instruction::Or => "or",
+            Instruction::Xor => "xor",
+            Instruction::Invert => "invert",
+            Instruction::Dup => "dup",
+            Instruction::Swap => "swap",
+            Instruction::Drop => "drop",
+            Instruction::Over => "over",
+            Instruction::ToReturn => ">r",
+            Instruction::FromReturn => "r>",
+            Instruction::FetchReturn => "r@",
+            Instruction::Store => "!",
+            Instruction::Fetch => "@",
+            Instruction::AddStore => "+!",
+            Instruction::Allot => "allot",
+            Instruction::Dot => ".",
+            Instruction::Emit => "emit",
+            Instruction::Type => "type",
+            Instruction::Cr => "cr",
+            Instruction::PrintStack => ".s",
+            Instruction::Number(_)
+            | Instruction::Call(_)
+            | Instruction::Return
+            | Instruction::Native(_)
+            | Instruction::Branch(_)
+            | Instruction::BranchIfZero(_)
+            | Instruction::Do
+            | Instruction::Loop(_)
+            | Instruction::LoopIndex
+            | Instruction::OuterLoopIndex
+            | Instruction::Print(_) => return None,
+        };
+        Some(name)
     }
 }
 
-impl Forth {
-    pub fn new() -> Forth {
+/// The result of a unary `op` on a constant, if it can be worked out ahead of
+/// time.
+fn fold_unary<T: Cell>(op: &Instruction<T>, x: &T) -> Option<T> {
+    let arith = Arithmetic::Checked;
+    match op {
+        Instruction::Negate => arith.neg(x).ok(),
+        Instruction::Abs => arith.abs(x).ok(),
+        Instruction::ZeroEq => Some(flag(*x == T::from(0))),
+        Instruction::Invert => Some(!x.clone()),
+        _ => None,
+    }
+}
+
+/// The result of a binary `op` on constants, if it can be worked out ahead of
+/// time. Anything that would fail is left to fail when run.
+fn fold_binary<T: Cell>(op: &Instruction<T>, x1: &T, x2: &T) -> Option<T> {
+    let arith = Arithmetic::Checked;
+    match op {
+        Instruction::Add => arith.add(x1, x2).ok(),
+        Instruction::Sub => arith.sub(x1, x2).ok(),
+        Instruction::Mul => arith.mul(x1, x2).ok(),
+        Instruction::Div => arith.div(x1, x2).ok(),
+        Instruction::Mod => arith.rem(x1, x2).ok(),
+        Instruction::Min => Some(x1.min(x2).clone()),
+        Instruction::Max => Some(x1.max(x2).clone()),
+        Instruction::Eq => Some(flag(x1 == x2)),
+        Instruction::Lt => Some(flag(x1 < x2)),
+        Instruction::Gt => Some(flag(x1 > x2)),
+        Instruction::And => Some(x1.clone() & x2.clone()),
+        Instruction::Or => Some(x1.clone() | x2.clone()),
+        Instruction::Xor => Some(x1.clone() ^ x2.clone()),
+        _ => None,
+    }
+}
+
+/// Converts a condition to a Forth truth value: all bits set for true.
+fn flag<T: Cell>(cond: bool) -> T {
+    if cond {
+        T::from(-1)
+    } else {
+        T::from(0)
+    }
+}
+
+/// The text of a string literal such as `." hello world"`, up to the
+/// closing `"`.
+fn parse_string<'a>(words: &mut Words<'a>) -> Result<&'a str, Error> {
+    words.parse('"').ok_or(Error::InvalidWord)
+}
+
+/// Words read before the dictionary is looked up, so a definition with one
+/// of their names could never be called.
+const RESERVED: &[&str] = &[":", ";", "variable", "constant", "see"];
+
+/// Checks the word following `:`, `variable` or `constant` can name a word.
+fn parse_name<T: Cell>(word: Option<&str>) -> Result<String, Error> {
+    let name = word.ok_or(Error::InvalidWord)?.to_ascii_lowercase();
+    if name.parse::<T>().is_ok() || RESERVED.contains(&name.as_str()) {
+        return Err(Error::InvalidWord);
+    }
+    Ok(name)
+}
+
+impl<T: Cell> Default for Forth<T> {
+    fn default() -> Self {
         Forth {
             stack: vec![],
+            code: vec![],
             words: vec![],
+            natives: vec![],
+            memory: vec![],
+            loops: vec![],
+            arithmetic: Arithmetic::default(),
+            optimization: Optimization::default(),
+            limits: Limits::default(),
+            steps: 0,
+            returns: vec![],
+            return_values: vec![],
+            output: Output::Buffer(vec![]),
+            tracer: None,
         }
     }
+}
+
+impl Forth {
+    pub fn new() -> Forth {
+        Self::default()
+    }
+}
+
+impl<T: Cell> Forth<T> {
+    /// Sends program output to `sink` instead of the in-memory buffer.
+    pub fn with_output(mut self, sink: impl Write + 'static) -> Self {
+        self.output = Output::Sink(Box::new(sink));
+        self
+    }
+
+    /// Selects how arithmetic overflow is handled; checked by default.
+    pub fn with_arithmetic(mut self, arithmetic: Arithmetic) -> Self {
+        self.arithmetic = arithmetic;
+        self
+    }
+
+    /// Selects how definitions are compiled; unoptimized by default.
+    pub fn with_optimization(mut self, optimization: Optimization) -> Self {
+        self.optimization = optimization;
+        self
+    }
+
+    /// Bounds the resources available to programs; unlimited by default.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Shows `tracer` each instruction before it runs. Returning an error from
+    /// it stops the program, which a step debugger can use to break.
+    pub fn with_tracer(mut self, tracer: impl FnMut(&Step<T>) -> ForthResult + 'static) -> Self {
+        self.tracer = Some(Box::new(tracer));
+        self
+    }
 
-    pub fn stack(&self) -> &[Value] {
+    pub fn stack(&self) -> &[T] {
         &self.stack
     }
 
-    fn parse_word<'a>(
+    /// Defines `name` to run a Rust closure on the data stack. Like any other
+    /// definition, it can be called from later definitions and shadowed by
+    /// redefining `name`.
+    pub fn register(
         &mut self,
-        word: &'a str,
-        remaining_input: &mut impl Iterator<Item = &'a str>,
+        name: &str,
+        word: impl FnMut(&mut Vec<T>) -> ForthResult + 'static,
     ) -> ForthResult {
-        if word == ":" {
-            self.parse_definition(remaining_input)
-        } else {
-            let instr = self.parse_normal_word(word)?;
-            self.eval_instruction(instr)
+        let name = parse_name::<T>(Some(name))?;
+        let idx = self.natives.len();
+        self.define_with(name, Instruction::Native(idx))?;
+        self.natives.push(Box::new(word));
+        Ok(())
+    }
+
+    /// Output written by the program so far, unless a sink was set with
+    /// `with_output`.
+    pub fn output(&self) -> &[u8] {
+        match &self.output {
+            Output::Buffer(buf) => buf,
+            Output::Sink(_) => &[],
         }
     }
 
-    fn parse_definition<'a>(&mut self, iter: &mut impl Iterator<Item = &'a str>) -> ForthResult {
-        if let Some(new_word) = iter.next() {
-            if new_word.parse::<Value>().is_ok() {
-                return Err(Error::InvalidWord);
+    /// Takes the buffered output, leaving the buffer empty.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        match &mut self.output {
+            Output::Buffer(buf) => std::mem::take(buf),
+            Output::Sink(_) => vec![],
+        }
+    }
+
+    /// Names of the user-defined words currently visible, newest first.
+    /// Shadowed definitions are not listed.
+    pub fn words(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = vec![];
+        for defn in self.words.iter().rev() {
+            if !names.contains(&defn.name.as_str()) {
+                names.push(&defn.name);
             }
+        }
+        names
+    }
 
-            let name = new_word.to_ascii_lowercase();
-            let mut body = vec![];
-            for word in iter {
-                if word == ";" {
-                    self.words.push(Definition { name, body });
-                    return Ok(());
-                } else {
-                    body.push(self.parse_normal_word(word)?)
+    fn parse_word<'a>(&mut self, word: &'a str, remaining_input: &mut Words<'a>) -> ForthResult {
+        match word.to_ascii_lowercase().as_str() {
+            ":" => self.parse_definition(remaining_input),
+            "variable" => self.parse_variable(remaining_input),
+            "constant" => self.parse_constant(remaining_input),
+            "see" => self.parse_see(remaining_input),
+            _ => self.parse_statement(word, remaining_input),
+        }
+    }
+
+    fn parse_definition<'a>(&mut self, iter: &mut Words<'a>) -> ForthResult {
+        let name = parse_name::<T>(iter.next())?;
+        self.check_room()?;
+
+        let start = self.code.len();
+        match self.compile_definition(iter) {
+            Ok(()) => {
+                self.define(name, start);
+                self.optimize_definition(self.words.len() - 1);
+                Ok(())
+            }
+            Err(e) => {
+                self.code.truncate(start);
+                Err(e)
+            }
+        }
+    }
+
+    fn compile_definition<'a>(&mut self, iter: &mut Words<'a>) -> ForthResult {
+        let mut compiling = Compiling::new(Some(self.words.len()));
+        while let Some(word) = iter.next() {
+            if word == ";" {
+                if !compiling.control.is_empty() {
+                    return Err(Error::InvalidWord);
                 }
+                return Ok(());
+            } else {
+                self.compile_word(word, iter, &mut compiling)?
             }
         }
         Err(Error::InvalidWord)
     }
 
-    fn parse_normal_word(&mut self, word: &str) -> Result<Instruction, Error> {
-        if word == ":" || word == ";" {
-            Err(Error::InvalidWord)
-        } else {
-            let lowercase = word.to_ascii_lowercase();
-            if let Some(call) = self.find_defn(&lowercase) {
-                Ok(call)
-            } else {
-                parse_builtin(&lowercase)
+    /// `variable name` reserves a memory cell and defines `name` to push its
+    /// address.
+    fn parse_variable<'a>(&mut self, iter: &mut Words<'a>) -> ForthResult {
+        let name = parse_name::<T>(iter.next())?;
+        self.check_room()?;
+        let addr = T::from_usize(self.memory.len()).ok_or(Error::OutOfMemory)?;
+        self.allot(T::from(1))?;
+        self.define_with(name, Instruction::Number(addr))
+    }
+
+    /// `x constant name` defines `name` to push `x`.
+    fn parse_constant<'a>(&mut self, iter: &mut Words<'a>) -> ForthResult {
+        let name = parse_name::<T>(iter.next())?;
+        self.check_room()?;
+        let x = self.stack.pop().ok_or(Error::StackUnderflow)?;
+        self.define_with(name, Instruction::Number(x))
+    }
+
+    /// `see name` writes out the source of `name`'s current definition.
+    fn parse_see<'a>(&mut self, iter: &mut Words<'a>) -> ForthResult {
+        let name = iter.next().ok_or(Error::InvalidWord)?;
+        let source = self.decompile(name)?;
+        self.write(format!("{}\n", source).as_bytes())
+    }
+
+    fn check_room(&self) -> ForthResult {
+        if self.words.len() >= self.limits.max_definitions {
+            return Err(Error::TooManyDefinitions);
+        }
+        Ok(())
+    }
+
+    /// Finishes defining `name` as the code from `start` onwards.
+    fn define(&mut self, name: String, start: usize) {
+        self.code.push(Instruction::Return);
+        self.words.push(Definition {
+            name,
+            body: start..self.code.len(),
+            source: start..self.code.len(),
+        });
+    }
+
+    /// Defines `name` as a single instruction.
+    fn define_with(&mut self, name: String, instr: Instruction<T>) -> ForthResult {
+        self.check_room()?;
+        let start = self.code.len();
+        self.code.push(instr);
+        self.define(name, start);
+        Ok(())
+    }
+
+    /// Compiles a word used outside of a definition, along with the rest of
+    /// any control structure it opens, and runs it.
+    fn parse_statement<'a>(&mut self, first: &'a str, iter: &mut Words<'a>) -> ForthResult {
+        let start = self.code.len();
+        let mut offsets = vec![];
+        let mut result = self.compile_statement(first, iter, &mut offsets);
+        if result.is_ok() {
+            let end = self.code.len();
+            let moved: Vec<usize> = match self.optimization {
+                Optimization::Off => (start..=end).collect(),
+                Optimization::Full => self.optimize(start..end, None),
+            };
+            let mut pc = moved[0];
+            result = self.run(&mut pc);
+            if result.is_err() {
+                // point at the word that failed, or at the call it failed in
+                let top = self.returns.first().map_or(pc, |frame| frame.ret) - 1;
+                let source = moved.partition_point(|&to| to <= top) - 1;
+                iter.point_at(offsets[source]);
             }
         }
+        self.code.truncate(start);
+        result
+    }
+
+    /// Compiles a statement, recording in `offsets` where in the input the
+    /// word behind each instruction starts.
+    fn compile_statement<'a>(
+        &mut self,
+        first: &'a str,
+        iter: &mut Words<'a>,
+        offsets: &mut Vec<usize>,
+    ) -> ForthResult {
+        let start = self.code.len();
+        let mut compiling = Compiling::new(None);
+        self.compile_word(first, iter, &mut compiling)?;
+        offsets.resize(self.code.len() - start, iter.offset());
+
+        while !compiling.control.is_empty() {
+            let word = iter.next().ok_or(Error::InvalidWord)?;
+            self.compile_word(word, iter, &mut compiling)?;
+            offsets.resize(self.code.len() - start, iter.offset());
+        }
+        self.code.push(Instruction::Return);
+        offsets.push(iter.offset());
+        Ok(())
+    }
+
+    fn compile_word<'a>(
+        &mut self,
+        word: &'a str,
+        iter: &mut Words<'a>,
+        compiling: &mut Compiling,
+    ) -> ForthResult {
+        let lowercase = word.to_ascii_lowercase();
+        // user definitions shadow built-in words, control words included
+        if let Some(call) = self.find_defn(&lowercase) {
+            self.code.push(call);
+            return Ok(());
+        }
+        if self.compile_control(&lowercase, &mut compiling.control)? {
+            return Ok(());
+        }
+
+        let instr = match lowercase.as_str() {
+            ".\"" => Instruction::Print(parse_string(iter)?.into()),
+            "s\"" => {
+                let (addr, len) = self.allot_string(parse_string(iter)?)?;
+                self.code.push(Instruction::Number(addr));
+                Instruction::Number(len)
+            }
+            "i" => Instruction::LoopIndex,
+            "j" => Instruction::OuterLoopIndex,
+            "exit" => {
+                compiling.defining.ok_or(Error::InvalidWord)?;
+                Instruction::Return
+            }
+            "recurse" => Instruction::Call(compiling.defining.ok_or(Error::InvalidWord)?),
+            _ => self.parse_normal_word(word)?,
+        };
+        self.code.push(instr);
+        Ok(())
     }
 
-    fn eval_instruction(&mut self, instr: Instruction) -> ForthResult {
+    /// Compiles `word` if it opens, continues or closes a control structure,
+    /// returning whether it did.
+    fn compile_control(&mut self, word: &str, control: &mut Vec<Control>) -> Result<bool, Error> {
+        let code = &mut self.code;
+        match word {
+            "if" => {
+                control.push(Control::If(code.len()));
+                code.push(Instruction::BranchIfZero(0));
+            }
+            "else" => match control.pop() {
+                Some(Control::If(cond)) => {
+                    control.push(Control::Else(code.len()));
+                    code.push(Instruction::Branch(0));
+                    code[cond] = Instruction::BranchIfZero(code.len());
+                }
+                _ => return Err(Error::InvalidWord),
+            },
+            "then" => match control.pop() {
+                Some(Control::If(cond)) => code[cond] = Instruction::BranchIfZero(code.len()),
+                Some(Control::Else(jump)) => code[jump] = Instruction::Branch(code.len()),
+                _ => return Err(Error::InvalidWord),
+            },
+            "do" => {
+                code.push(Instruction::Do);
+                control.push(Control::Do(code.len()));
+            }
+            "loop" => match control.pop() {
+                Some(Control::Do(start)) => code.push(Instruction::Loop(start)),
+                _ => return Err(Error::InvalidWord),
+            },
+            "begin" => control.push(Control::Begin(code.len())),
+            "until" => match control.pop() {
+                Some(Control::Begin(begin)) => code.push(Instruction::BranchIfZero(begin)),
+                _ => return Err(Error::InvalidWord),
+            },
+            "while" => match control.pop() {
+                Some(Control::Begin(begin)) => {
+                    control.push(Control::While {
+                        begin,
+                        cond: code.len(),
+                    });
+                    code.push(Instruction::BranchIfZero(0));
+                }
+                _ => return Err(Error::InvalidWord),
+            },
+            "repeat" => match control.pop() {
+                Some(Control::While { begin, cond }) => {
+                    code.push(Instruction::Branch(begin));
+                    code[cond] = Instruction::BranchIfZero(code.len());
+                }
+                _ => return Err(Error::InvalidWord),
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Optimizes definition `idx` from its source, if optimization is on.
+    fn optimize_definition(&mut self, idx: usize) {
+        if self.optimization == Optimization::Full {
+            let start = self.code.len();
+            self.optimize(self.words[idx].source.clone(), Some(idx));
+            self.words[idx].body = start..self.code.len();
+        }
+    }
+
+    /// Compiles an optimized copy of the code in `source` onto the end of the
+    /// code, where `defining` is the definition it belongs to, if any.
+    /// Returns where each instruction of the source, and its end, ended up
+    /// in the copy.
+    fn optimize(&mut self, source: Range<usize>, defining: Option<usize>) -> Vec<usize> {
+        let targets: Vec<usize> = self.code[source.clone()]
+            .iter()
+            .filter_map(|instr| match instr {
+                Instruction::Branch(target)
+                | Instruction::BranchIfZero(target)
+                | Instruction::Loop(target) => Some(*target),
+                _ => None,
+            })
+            .collect();
+
+        let start = self.code.len();
+        let mut fence = start;
+        let mut moved = Vec::with_capacity(source.len() + 1);
+        for pc in source.clone() {
+            if targets.contains(&pc) {
+                fence = self.code.len();
+            }
+            moved.push(self.code.len());
+            match self.code[pc].clone() {
+                // a word can't be inlined into itself
+                instr @ Instruction::Call(callee) if Some(callee) == defining => {
+                    self.code.push(instr)
+                }
+                instr => self.emit(instr, fence),
+            }
+        }
+        moved.push(self.code.len());
+        // instructions folded into a constant end up where it is
+        for i in (0..source.len()).rev() {
+            moved[i] = moved[i].min(moved[i + 1]);
+        }
+
+        for instr in &mut self.code[start..] {
+            if let Instruction::Branch(target)
+            | Instruction::BranchIfZero(target)
+            | Instruction::Loop(target) = instr
+            {
+                *target = moved[*target - source.start];
+            }
+        }
+        moved
+    }
+
+    /// Appends `instr` to the code being optimized, inlining small definitions
+    /// and folding arithmetic on constants. Code before `fence` is left alone.
+    fn emit(&mut self, instr: Instruction<T>, fence: usize) {
+        if let Instruction::Call(idx) = instr {
+            if let Some(body) = self.inline_body(idx) {
+                for instr in body {
+                    self.emit(instr, fence);
+                }
+                return;
+            }
+        }
+
+        self.code.push(instr);
+        let len = self.code.len();
+        let code = &self.code[fence..];
+        let folded = match code {
+            [.., Instruction::Number(x), op] => fold_unary(op, x).map(|x| (2, x)),
+            _ => None,
+        }
+        .or_else(|| match code {
+            [.., Instruction::Number(x1), Instruction::Number(x2), op] => {
+                fold_binary(op, x1, x2).map(|x| (3, x))
+            }
+            _ => None,
+        });
+
+        if let Some((replaced, x)) = folded {
+            self.code.truncate(len - replaced);
+            self.code.push(Instruction::Number(x));
+        }
+    }
+
+    /// The body of definition `idx` without its `Return`, if it is short and
+    /// has no jumps, return stack use or recursion, so can be copied into its
+    /// callers.
+    fn inline_body(&self, idx: usize) -> Option<Vec<Instruction<T>>> {
+        let body = &self.code[self.words[idx].body.clone()];
+        let body = &body[..body.len() - 1];
+        let inlinable = body.len() <= INLINE_LIMIT
+            && body.iter().all(|instr| match instr {
+                Instruction::Branch(_)
+                | Instruction::BranchIfZero(_)
+                | Instruction::Loop(_)
+                | Instruction::Return
+                | Instruction::ToReturn
+                | Instruction::FromReturn
+                | Instruction::FetchReturn => false,
+                Instruction::Call(callee) => *callee != idx,
+                _ => true,
+            });
+        inlinable.then(|| body.to_vec())
+    }
+
+    fn parse_normal_word(&mut self, word: &str) -> Result<Instruction<T>, Error> {
+        let lowercase = word.to_ascii_lowercase();
+        match lowercase.as_str() {
+            ":" | ";" | "variable" | "constant" | "see" => Err(Error::InvalidWord),
+            _ => parse_builtin(&lowercase),
+        }
+    }
+
+    fn eval_instruction(&mut self, instr: Instruction<T>) -> ForthResult {
+        let arith = self.arithmetic;
         match instr {
-            Instruction::Add => self.arithmetic("+"),
-            Instruction::Sub => self.arithmetic("-"),
-            Instruction::Mul => self.arithmetic("*"),
-            Instruction::Div => self.arithmetic("/"),
+            Instruction::Add => self.binary(|x1, x2| arith.add(&x1, &x2)),
+            Instruction::Sub => self.binary(|x1, x2| arith.sub(&x1, &x2)),
+            Instruction::Mul => self.binary(|x1, x2| arith.mul(&x1, &x2)),
+            Instruction::Div => self.binary(|x1, x2| arith.div(&x1, &x2)),
+            Instruction::Mod => self.binary(|x1, x2| arith.rem(&x1, &x2)),
+            Instruction::DivMod => self.div_mod(),
+            Instruction::Negate => self.unary(|x| arith.neg(&x)),
+            Instruction::Abs => self.unary(|x| arith.abs(&x)),
+            Instruction::Min => self.binary(|x1, x2| Ok(x1.min(x2))),
+            Instruction::Max => self.binary(|x1, x2| Ok(x1.max(x2))),
+            Instruction::Eq => self.binary(|x1, x2| Ok(flag(x1 == x2))),
+            Instruction::Lt => self.binary(|x1, x2| Ok(flag(x1 < x2))),
+            Instruction::Gt => self.binary(|x1, x2| Ok(flag(x1 > x2))),
+            Instruction::ZeroEq => self.unary(|x| Ok(flag(x == T::from(0)))),
+            Instruction::And => self.binary(|x1, x2| Ok(x1 & x2)),
+            Instruction::Or => self.binary(|x1, x2| Ok(x1 | x2)),
+            Instruction::Xor => self.binary(|x1, x2| Ok(x1 ^ x2)),
+            Instruction::Invert => self.unary(|x| Ok(!x)),
             Instruction::Dup => self.dup(),
             Instruction::Swap => self.swap(),
             Instruction::Drop => self.drop(),
             Instruction::Over => self.over(),
-            Instruction::Number(n) => {
-                self.stack.push(n);
+            Instruction::ToReturn => {
+                let x = self.stack.pop().ok_or(Error::StackUnderflow)?;
+                if self.return_values.len() >= self.limits.max_stack_depth {
+                    return Err(Error::StackOverflow);
+                }
+                self.return_values.push(x);
+                Ok(())
+            }
+            Instruction::FromReturn => {
+                let x = self.pop_return()?;
+                self.push(x)
+            }
+            Instruction::FetchReturn => {
+                let x = self.pop_return()?;
+                self.return_values.push(x.clone());
+                self.push(x)
+            }
+            Instruction::Number(n) => self.push(n),
+            Instruction::Native(idx) => {
+                (self.natives[idx])(&mut self.stack)?;
+                if self.stack.len() > self.limits.max_stack_depth {
+                    return Err(Error::StackOverflow);
+                }
+                Ok(())
+            }
+            Instruction::Do => {
+                let index = self.stack.pop().ok_or(Error::StackUnderflow)?;
+                let limit = self.stack.pop().ok_or(Error::StackUnderflow)?;
+                self.loops.push(LoopFrame { index, limit });
                 Ok(())
             }
-            Instruction::Call(idx) => self.call(idx),
+            Instruction::Store => {
+                let addr = self.pop_address()?;
+                let x = self.stack.pop().ok_or(Error::StackUnderflow)?;
+                self.memory[addr] = x;
+                Ok(())
+            }
+            Instruction::Fetch => {
+                let addr = self.pop_address()?;
+                self.push(self.memory[addr].clone())
+            }
+            Instruction::AddStore => {
+                let addr = self.pop_address()?;
+                let x = self.stack.pop().ok_or(Error::StackUnderflow)?;
+                self.memory[addr] = arith.add(&self.memory[addr], &x)?;
+                Ok(())
+            }
+            Instruction::Allot => {
+                let n = self.stack.pop().ok_or(Error::StackUnderflow)?;
+                self.allot(n)
+            }
+            Instruction::Dot => {
+                let x = self.stack.pop().ok_or(Error::StackUnderflow)?;
+                self.write(format!("{} ", x).as_bytes())
+            }
+            Instruction::Emit => {
+                let x = self.stack.pop().ok_or(Error::StackUnderflow)?;
+                self.write(&[x.low_byte()])
+            }
+            Instruction::Type => {
+                let len = self.stack.pop().ok_or(Error::StackUnderflow)?;
+                let addr = self.stack.pop().ok_or(Error::StackUnderflow)?;
+                let range = addr
+                    .to_usize()
+                    .zip(len.to_usize())
+                    .and_then(|(addr, len)| Some(addr..addr.checked_add(len)?))
+                    .filter(|range| range.end <= self.memory.len())
+                    .ok_or(Error::InvalidAddress)?;
+                let bytes: Vec<u8> = self.memory[range].iter().map(T::low_byte).collect();
+                self.write(&bytes)
+            }
+            Instruction::Cr => self.write(b"\n"),
+            Instruction::PrintStack => {
+                let mut line = format!("<{}> ", self.stack.len());
+                for x in &self.stack {
+                    line.push_str(&format!("{} ", x));
+                }
+                self.write(line.as_bytes())
+            }
+            Instruction::Print(text) => self.write(text.as_bytes()),
+            Instruction::LoopIndex => self.loop_index(1),
+            Instruction::OuterLoopIndex => self.loop_index(2),
+            // transfers of control are handled by `run`
+            Instruction::Call(_)
+            | Instruction::Return
+            | Instruction::Branch(_)
+            | Instruction::BranchIfZero(_)
+            | Instruction::Loop(_) => Err(Error::InvalidWord),
         }
     }
 
-    fn call(&mut self, idx: usize) -> ForthResult {
-        let def = self.words[idx].body.clone();
-        for instr in def {
-            self.eval_instruction(instr)?;
+    /// Runs the code at `pc` until it returns to the top level. On error `pc`
+    /// is left just past the instruction that failed, and frames are left on
+    /// the return stack, for `Diagnostic::call_stack`.
+    fn run(&mut self, pc: &mut usize) -> ForthResult {
+        loop {
+            let instr = self.code[*pc].clone();
+            *pc += 1;
+
+            if matches!(instr, Instruction::Return) && self.returns.is_empty() {
+                return Ok(());
+            }
+            self.trace(&instr)?;
+
+            if let Instruction::Return = instr {
+                if let Some(frame) = self.returns.pop() {
+                    self.loops.truncate(frame.loops);
+                    self.return_values.truncate(frame.values);
+                    *pc = frame.ret;
+                }
+                continue;
+            }
+
+            if self.steps >= self.limits.max_steps {
+                return Err(Error::StepLimitExceeded);
+            }
+            self.steps += 1;
+
+            match instr {
+                Instruction::Call(idx) => {
+                    if self.returns.len() >= self.limits.max_call_depth {
+                        return Err(Error::CallDepthExceeded);
+                    }
+                    self.returns.push(Frame {
+                        word: idx,
+                        ret: *pc,
+                        loops: self.loops.len(),
+                        values: self.return_values.len(),
+                    });
+                    *pc = self.words[idx].body.start;
+                }
+                Instruction::Branch(target) => *pc = target,
+                Instruction::BranchIfZero(target) => {
+                    if self.stack.pop().ok_or(Error::StackUnderflow)? == T::from(0) {
+                        *pc = target;
+                    }
+                }
+                Instruction::Loop(start) => {
+                    let frame = self.loops.last_mut().ok_or(Error::StackUnderflow)?;
+                    frame.index = frame
+                        .index
+                        .checked_add(&T::from(1))
+                        .ok_or(Error::Overflow)?;
+                    if frame.index < frame.limit {
+                        *pc = start;
+                    } else {
+                        self.loops.pop();
+                    }
+                }
+                _ => self.eval_instruction(instr)?,
+            }
         }
+    }
+
+    /// Pushes the index of the `depth`th innermost running loop.
+    fn loop_index(&mut self, depth: usize) -> ForthResult {
+        let index = self
+            .loops
+            .len()
+            .checked_sub(depth)
+            .and_then(|idx| self.loops.get(idx))
+            .ok_or(Error::StackUnderflow)?
+            .index
+            .clone();
+
+        self.push(index)?;
         Ok(())
     }
 
-    fn find_defn(&self, word: &str) -> Option<Instruction> {
+    fn find_defn(&self, word: &str) -> Option<Instruction<T>> {
         for (idx, defn) in self.words.iter().enumerate().rev() {
             if defn.name == word {
                 return Some(Instruction::Call(idx));
@@ -153,39 +1260,176 @@ impl Forth {
     }
 
     pub fn eval(&mut self, input: &str) -> ForthResult {
-        let mut iter = input.split_ascii_whitespace();
+        self.eval_diagnostic(input).map_err(Error::from)
+    }
 
-        while let Some(word) = iter.next() {
-            self.parse_word(word, &mut iter)?;
+    /// Like `eval`, but reports where in `input` an error occurred.
+    pub fn eval_diagnostic(&mut self, input: &str) -> Result<(), Diagnostic> {
+        self.steps = 0;
+        self.returns.clear();
+        self.loops.clear();
+        self.return_values.clear();
+
+        let mut words = Words::new(input);
+        let result = self.parse_input(&mut words);
+        let flushed = self.flush();
+        result.and(flushed).map_err(|error| {
+            let offset = words.offset();
+            Diagnostic {
+                error,
+                word: words.last.to_string(),
+                offset,
+                line: input[..offset].matches('\n').count() + 1,
+                call_stack: self
+                    .returns
+                    .iter()
+                    .map(|frame| self.words[frame.word].name.clone())
+                    .collect(),
+            }
+        })
+    }
+
+    /// Like `eval`, but on error the stack, memory and dictionary are restored
+    /// to what they were before the call. Output already written is kept.
+    pub fn eval_atomic(&mut self, input: &str) -> ForthResult {
+        let stack = self.stack.clone();
+        let memory = self.memory.clone();
+        let code = self.code.len();
+        let definitions = self.words.len();
+
+        let result = self.eval(input);
+        if result.is_err() {
+            self.stack = stack;
+            self.memory = memory;
+            self.code.truncate(code);
+            self.words.truncate(definitions);
         }
-        Ok(())
+        result
     }
 
-    fn arithmetic(&mut self, op: &str) -> ForthResult {
-        let x1 = self.stack.pop().ok_or(Error::StackUnderflow)?;
-        let x2 = self.stack.pop().ok_or(Error::StackUnderflow)?;
+    fn parse_input(&mut self, words: &mut Words) -> ForthResult {
+        while let Some(word) = words.next() {
+            self.parse_word(word, words)?;
+        }
+        Ok(())
+    }
 
-        match op {
-            "+" => self.stack.push(x1 + x2),
-            "-" => self.stack.push(x2 - x1),
-            "*" => self.stack.push(x1 * x2),
-            "/" => {
-                if x1 == 0 {
-                    return Err(Error::DivisionByZero);
-                };
+    fn flush(&mut self) -> ForthResult {
+        match &mut self.output {
+            Output::Buffer(_) => Ok(()),
+            Output::Sink(sink) => sink.flush().map_err(|_| Error::Output),
+        }
+    }
 
-                self.stack.push(x2 / x1);
+    fn write(&mut self, bytes: &[u8]) -> ForthResult {
+        match &mut self.output {
+            Output::Buffer(buf) => {
+                buf.extend_from_slice(bytes);
+                Ok(())
             }
+            Output::Sink(sink) => sink.write_all(bytes).map_err(|_| Error::Output),
+        }
+    }
+
+    /// Pops an address, checking it refers to a reserved cell.
+    fn pop_address(&mut self) -> Result<usize, Error> {
+        let addr = self.stack.pop().ok_or(Error::StackUnderflow)?;
+        addr.to_usize()
+            .filter(|&addr| addr < self.memory.len())
+            .ok_or(Error::InvalidAddress)
+    }
+
+    /// Copies `text` into newly reserved memory, a byte to a cell, returning
+    /// its address and length.
+    fn allot_string(&mut self, text: &str) -> Result<(T, T), Error> {
+        let addr = T::from_usize(self.memory.len()).ok_or(Error::OutOfMemory)?;
+        let len = T::from_usize(text.len()).ok_or(Error::OutOfMemory)?;
+        self.allot(len.clone())?;
+
+        let start = self.memory.len() - text.len();
+        for (cell, byte) in self.memory[start..].iter_mut().zip(text.bytes()) {
+            *cell = T::from(i32::from(byte));
+        }
+        Ok((addr, len))
+    }
 
-            _ => (),
+    /// Reserves `n` more memory cells, or releases them if `n` is negative.
+    fn allot(&mut self, n: T) -> ForthResult {
+        let len = if n >= T::from(0) {
+            n.to_usize()
+                .and_then(|n| self.memory.len().checked_add(n))
+                .ok_or(Error::OutOfMemory)?
+        } else {
+            n.checked_neg()
+                .and_then(|n| n.to_usize())
+                .and_then(|n| self.memory.len().checked_sub(n))
+                .ok_or(Error::InvalidAddress)?
         };
+        if len > self.limits.max_cells {
+            return Err(Error::OutOfMemory);
+        }
+
+        // unbounded by default, so ask for the space rather than abort
+        let more = len.saturating_sub(self.memory.len());
+        self.memory
+            .try_reserve_exact(more)
+            .map_err(|_| Error::OutOfMemory)?;
+        self.memory.resize(len, T::from(0));
+        Ok(())
+    }
+
+    fn push(&mut self, value: T) -> ForthResult {
+        if self.stack.len() >= self.limits.max_stack_depth {
+            return Err(Error::StackOverflow);
+        }
+
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Pops a value that the running word moved to the return stack. Values
+    /// its callers moved there are out of reach.
+    fn pop_return(&mut self) -> Result<T, Error> {
+        let base = self.returns.last().map_or(0, |frame| frame.values);
+        if self.return_values.len() <= base {
+            return Err(Error::StackUnderflow);
+        }
+        self.return_values.pop().ok_or(Error::StackUnderflow)
+    }
+
+    /// Replaces the top value `x` with `op(x)`.
+    fn unary(&mut self, op: impl FnOnce(T) -> Result<T, Error>) -> ForthResult {
+        let x = self.stack.pop().ok_or(Error::StackUnderflow)?;
+
+        self.push(op(x)?)?;
+        Ok(())
+    }
+
+    /// Replaces the top two values `x1 x2` with `op(x1, x2)`.
+    fn binary(&mut self, op: impl FnOnce(T, T) -> Result<T, Error>) -> ForthResult {
+        let x2 = self.stack.pop().ok_or(Error::StackUnderflow)?;
+        let x1 = self.stack.pop().ok_or(Error::StackUnderflow)?;
+
+        self.push(op(x1, x2)?)?;
+        Ok(())
+    }
+
+    fn div_mod(&mut self) -> ForthResult {
+        let x2 = self.stack.pop().ok_or(Error::StackUnderflow)?;
+        let x1 = self.stack.pop().ok_or(Error::StackUnderflow)?;
+
+        let rem = self.arithmetic.rem(&x1, &x2)?;
+        let quot = self.arithmetic.div(&x1, &x2)?;
+
+        self.push(rem)?;
+        self.push(quot)?;
         Ok(())
     }
 
     fn dup(&mut self) -> ForthResult {
         let end = self.stack.last().cloned().ok_or(Error::StackUnderflow)?;
 
-        self.stack.push(end);
+        self.push(end)?;
         Ok(())
     }
 
@@ -199,8 +1443,8 @@ impl Forth {
         let x1 = self.stack.pop().ok_or(Error::StackUnderflow)?;
         let x2 = self.stack.pop().ok_or(Error::StackUnderflow)?;
 
-        self.stack.push(x1);
-        self.stack.push(x2);
+        self.push(x1)?;
+        self.push(x2)?;
         Ok(())
     }
 
@@ -208,9 +1452,9 @@ impl Forth {
         let x1 = self.stack.pop().ok_or(Error::StackUnderflow)?;
         let x2 = self.stack.pop().ok_or(Error::StackUnderflow)?;
 
-        self.stack.push(x2);
-        self.stack.push(x1);
-        self.stack.push(x2);
+        self.push(x2.clone())?;
+        self.push(x1)?;
+        self.push(x2)?;
         Ok(())
     }
 }
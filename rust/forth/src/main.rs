@@ -0,0 +1,8 @@
+use std::io;
+
+use forth::{repl, Forth};
+
+fn main() -> io::Result<()> {
+    let mut forth = Forth::new();
+    repl(&mut forth, io::stdin().lock(), io::stdout().lock())
+}
@@ -0,0 +1,153 @@
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+use crate::{Cell, Forth, Words};
+
+/// Runs an interactive session, reading lines of Forth from `input` and
+/// answering on `output`.
+///
+/// After each line the stack is shown, followed by `ok`, or the error that
+/// stopped the line. A definition or control structure left open at the end
+/// of a line carries on to the next, and one still open at the end of the
+/// input is answered with its error. Outside definitions, `words` lists the
+/// user-defined words and `bye` ends the session, wherever they appear in a
+/// line, unless a user definition has taken their name.
+///
+/// Program output is passed on from `forth`'s buffer, so it shouldn't be
+/// given a sink with `with_output`.
+pub fn repl<T: Cell>(
+    forth: &mut Forth<T>,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut pending = String::new();
+    for line in input.lines() {
+        pending.push_str(&line?);
+        pending.push('\n');
+        if scan(forth, &pending, 0).open > 0 {
+            continue;
+        }
+        if !answer(forth, &std::mem::take(&mut pending), &mut output)? {
+            return Ok(());
+        }
+    }
+    if !pending.trim().is_empty() {
+        answer(forth, &pending, &mut output)?;
+    }
+    output.flush()
+}
+
+/// Evaluates `text` and writes out the answer. Returns whether the session
+/// carries on, which it doesn't after `bye`.
+fn answer<T: Cell>(forth: &mut Forth<T>, text: &str, output: &mut impl Write) -> io::Result<bool> {
+    let mut from = 0;
+    let mut shown = vec![];
+    let mut ok = true;
+    while let Some((command, start, end)) = scan(forth, text, from).command {
+        ok &= eval(forth, text, from..start, &mut shown);
+        if command == "bye" {
+            output.write_all(&shown)?;
+            output.flush()?;
+            return Ok(false);
+        }
+        let words = forth.words();
+        if !words.is_empty() {
+            shown.extend_from_slice(words.join(" ").as_bytes());
+            shown.push(b'\n');
+        }
+        from = end;
+    }
+    if eval(forth, text, from..text.len(), &mut shown) && ok {
+        echo(forth, &mut shown);
+    }
+    output.write_all(&shown)?;
+    output.flush()?;
+    Ok(true)
+}
+
+/// Evaluates the `part` of `text` between commands, adding its output and
+/// any error to `shown`. Returns whether it ran without error.
+fn eval<T: Cell>(
+    forth: &mut Forth<T>,
+    text: &str,
+    part: Range<usize>,
+    shown: &mut Vec<u8>,
+) -> bool {
+    let result = forth.eval_diagnostic(&text[part.clone()]);
+    shown.extend(forth.take_output());
+    match result {
+        Ok(()) => true,
+        Err(mut e) => {
+            // point at the error in the whole text, not just this part
+            e.offset += part.start;
+            e.line += text[..part.start].matches('\n').count();
+            shown.extend_from_slice(format!("error: {}\n", e).as_bytes());
+            false
+        }
+    }
+}
+
+/// Shows the stack as `.s` would, then `ok`. The stack is left out if the
+/// line has just shown it with `.s`.
+fn echo<T: Cell>(forth: &Forth<T>, shown: &mut Vec<u8>) {
+    let mut stack = format!("<{}> ", forth.stack().len());
+    for x in forth.stack() {
+        stack.push_str(&format!("{} ", x));
+    }
+    if !shown.ends_with(stack.as_bytes()) {
+        shown.extend_from_slice(stack.as_bytes());
+    }
+    shown.extend_from_slice(b"ok\n");
+}
+
+/// What `scan` found in some input.
+struct Scan {
+    /// Definitions and control structures left open at the end.
+    open: i32,
+    /// The first `words` or `bye` command outside a definition, with where
+    /// it starts and ends.
+    command: Option<(&'static str, usize, usize)>,
+}
+
+/// Reads `text` from `from` on as the interpreter would, without running
+/// it. Words the dictionary defines, or that `text` defines before using
+/// them, are user words whatever they're called.
+fn scan<T: Cell>(forth: &Forth<T>, text: &str, from: usize) -> Scan {
+    let mut defined: Vec<String> = forth.words().iter().map(|name| name.to_string()).collect();
+    let mut found = Scan {
+        open: 0,
+        command: None,
+    };
+    let mut words = Words::new(&text[from..]);
+    while let Some(word) = words.next() {
+        let word = word.to_ascii_lowercase();
+        if defined.contains(&word) {
+            continue;
+        }
+        match word.as_str() {
+            ".\"" | "s\"" => {
+                words.parse('"');
+            }
+            ":" | "variable" | "constant" => {
+                if word == ":" {
+                    found.open += 1;
+                }
+                if let Some(name) = words.next() {
+                    defined.push(name.to_ascii_lowercase());
+                }
+            }
+            "see" => {
+                words.next();
+            }
+            "if" | "do" | "begin" => found.open += 1,
+            ";" | "then" | "loop" | "until" | "repeat" => found.open -= 1,
+            "words" | "bye" if found.open == 0 && found.command.is_none() => {
+                let command = if word == "bye" { "bye" } else { "words" };
+                let start = from + words.offset();
+                found.command = Some((command, start, start + word.len()));
+            }
+            _ => {}
+        }
+    }
+    found
+}
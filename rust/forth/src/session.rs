@@ -0,0 +1,220 @@
+use crate::{parse_builtin, parse_name, Cell, Definition, Error, Forth, ForthResult, Instruction};
+
+const HEADER: &str = "forth-session 1";
+
+impl<T: Cell> Forth<T> {
+    /// Writes the stack, memory and dictionary out as text that
+    /// `restore_session` can read back, one compiled instruction per line.
+    /// Definitions are saved unoptimized, and optimized again on restore if
+    /// optimization is on.
+    ///
+    /// Native words are saved by the order they were registered in, so the
+    /// same words must be registered, in the same order, before restoring.
+    pub fn save_session(&self) -> String {
+        let mut out = String::new();
+        out.push_str(HEADER);
+        out.push('\n');
+        write_values(&mut out, "stack", &self.stack);
+        write_values(&mut out, "memory", &self.memory);
+
+        for defn in &self.words {
+            out.push_str(&format!("word {}\n", defn.name));
+            for instr in &self.code[defn.source.clone()] {
+                out.push_str(&write_instruction(instr, defn.source.start));
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Replaces the stack, memory and dictionary with a session written by
+    /// `save_session`. Nothing is changed if the session can't be read or
+    /// doesn't fit within the limits.
+    pub fn restore_session(&mut self, session: &str) -> ForthResult {
+        let mut lines = session.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(Error::InvalidSession);
+        }
+        let stack = read_values(lines.next(), "stack")?;
+        let memory = read_values(lines.next(), "memory")?;
+
+        let mut code = vec![];
+        let mut words: Vec<Definition> = vec![];
+        for line in lines {
+            if let Some(name) = line.strip_prefix("word ") {
+                let name = parse_name::<T>(Some(name)).map_err(|_| Error::InvalidSession)?;
+                words.push(Definition {
+                    name,
+                    body: code.len()..code.len(),
+                    source: code.len()..code.len(),
+                });
+            } else {
+                let defn = words.last_mut().ok_or(Error::InvalidSession)?;
+                code.push(read_instruction(line, defn.body.start)?);
+                defn.body.end = code.len();
+                defn.source.end = code.len();
+            }
+        }
+
+        for (own, defn) in words.iter().enumerate() {
+            let body = &code[defn.body.clone()];
+            if !matches!(body.last(), Some(Instruction::Return)) {
+                return Err(Error::InvalidSession);
+            }
+            let valid = body.iter().all(|instr| match *instr {
+                // the compiler only calls earlier definitions, or itself
+                Instruction::Call(idx) => idx <= own,
+                Instruction::Native(idx) => idx < self.natives.len(),
+                Instruction::Branch(target)
+                | Instruction::BranchIfZero(target)
+                | Instruction::Loop(target) => defn.body.contains(&target),
+                _ => true,
+            });
+            if !valid {
+                return Err(Error::InvalidSession);
+            }
+        }
+
+        if stack.len() > self.limits.max_stack_depth {
+            return Err(Error::StackOverflow);
+        }
+        if memory.len() > self.limits.max_cells {
+            return Err(Error::OutOfMemory);
+        }
+        if words.len() > self.limits.max_definitions {
+            return Err(Error::TooManyDefinitions);
+        }
+
+        self.stack = stack;
+        self.memory = memory;
+        self.code = code;
+        self.words = words;
+        for idx in 0..self.words.len() {
+            self.optimize_definition(idx);
+        }
+        Ok(())
+    }
+}
+
+fn write_values<T: Cell>(out: &mut String, tag: &str, values: &[T]) {
+    out.push_str(tag);
+    for x in values {
+        out.push_str(&format!(" {}", x));
+    }
+    out.push('\n');
+}
+
+/// Writes one instruction of a definition starting at `start` in the code.
+/// Jump targets are saved relative to `start`.
+fn write_instruction<T: Cell>(instr: &Instruction<T>, start: usize) -> String {
+    match instr {
+        Instruction::Number(n) => format!("lit {}", n),
+        Instruction::Call(idx) => format!("call {}", idx),
+        Instruction::Return => "return".to_string(),
+        Instruction::Native(idx) => format!("native {}", idx),
+        Instruction::Branch(target) => format!("branch {}", target - start),
+        Instruction::BranchIfZero(target) => format!("0branch {}", target - start),
+        Instruction::Do => "do".to_string(),
+        Instruction::Loop(target) => format!("loop {}", target - start),
+        Instruction::LoopIndex => "i".to_string(),
+        Instruction::OuterLoopIndex => "j".to_string(),
+        Instruction::Print(text) => format!(".\" {}", escape(text)),
+        _ => instr.builtin_name().unwrap_or_default().to_string(),
+    }
+}
+
+fn read_values<T: Cell>(line: Option<&str>, tag: &str) -> Result<Vec<T>, Error> {
+    let mut fields = line.ok_or(Error::InvalidSession)?.split_ascii_whitespace();
+    if fields.next() != Some(tag) {
+        return Err(Error::InvalidSession);
+    }
+    fields
+        .map(|field| field.parse().map_err(|_| Error::InvalidSession))
+        .collect()
+}
+
+/// Reads one instruction of a definition starting at `start` in the code.
+fn read_instruction<T: Cell>(line: &str, start: usize) -> Result<Instruction<T>, Error> {
+    if let Some(text) = line.strip_prefix(".\" ") {
+        return Ok(Instruction::Print(unescape(text)?.into()));
+    }
+
+    let (op, arg) = line.split_once(' ').unwrap_or((line, ""));
+    let index = || arg.parse::<usize>().map_err(|_| Error::InvalidSession);
+    let target = || start.checked_add(index()?).ok_or(Error::InvalidSession);
+    match op {
+        "lit" => arg
+            .parse()
+            .map(Instruction::Number)
+            .map_err(|_| Error::InvalidSession),
+        "call" => index().map(Instruction::Call),
+        "return" => Ok(Instruction::Return),
+        "native" => index().map(Instruction::Native),
+        "branch" => target().map(Instruction::Branch),
+        "0branch" => target().map(Instruction::BranchIfZero),
+        "do" => Ok(Instruction::Do),
+        "loop" => target().map(Instruction::Loop),
+        "i" => Ok(Instruction::LoopIndex),
+        "j" => Ok(Instruction::OuterLoopIndex),
+        _ => match parse_builtin(op) {
+            Ok(Instruction::Number(_)) | Err(_) => Err(Error::InvalidSession),
+            Ok(instr) => Ok(instr),
+        },
+    }
+}
+
+/// Escapes backslashes and control characters, so the text stays on one
+/// line and reads back unchanged.
+fn escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(text: &str) -> Result<String, Error> {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('\\') => out.push('\\'),
+                Some('n') => out.push('\n'),
+                Some('r') => out.push('\r'),
+                Some('t') => out.push('\t'),
+                Some('u') => out.push(unescape_code(&mut chars)?),
+                _ => return Err(Error::InvalidSession),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    Ok(out)
+}
+
+/// Reads the `{hex}` following `\u`.
+fn unescape_code(chars: &mut std::str::Chars) -> Result<char, Error> {
+    if chars.next() != Some('{') {
+        return Err(Error::InvalidSession);
+    }
+    let mut hex = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => hex.push(c),
+            None => return Err(Error::InvalidSession),
+        }
+    }
+    u32::from_str_radix(&hex, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or(Error::InvalidSession)
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+use std::io::Write;
+
+use crate::{Cell, Error, Forth, ForthResult, Instruction, Value};
+
+/// An instruction about to be run, as shown to a tracer set with
+/// `Forth::with_tracer`.
+///
+/// Displays as one line of a readable trace: the instruction, indented by
+/// the call depth, followed by the stack in the same form as `.s`.
+pub struct Step<'a, T = Value> {
+    pub stack: &'a [T],
+    pub instruction: &'a Instruction<T>,
+    /// The instruction as Forth source, with calls resolved to word names.
+    pub word: String,
+    /// Calls to user-defined words currently running.
+    pub depth: usize,
+}
+
+impl<T: Cell> fmt::Display for Step<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let word = format!("{}{}", "  ".repeat(self.depth), self.word);
+        write!(f, "{:<12} <{}>", word, self.stack.len())?;
+        for x in self.stack {
+            write!(f, " {}", x)?;
+        }
+        Ok(())
+    }
+}
+
+/// A tracer that writes each step to `sink` on its own line.
+pub fn print_trace<T: Cell>(mut sink: impl Write) -> impl FnMut(&Step<T>) -> ForthResult {
+    move |step| writeln!(sink, "{}", step).map_err(|_| Error::Output)
+}
+
+impl<T: Cell> Forth<T> {
+    /// Shows `instr` to the tracer, if there is one. An error from the tracer
+    /// stops the program as if the instruction had failed.
+    pub(crate) fn trace(&mut self, instr: &Instruction<T>) -> ForthResult {
+        if self.tracer.is_none() {
+            return Ok(());
+        }
+
+        let word = match instr {
+            Instruction::Branch(_) => "branch".to_string(),
+            Instruction::BranchIfZero(_) => "0branch".to_string(),
+            _ => self.decompile_instruction(instr),
+        };
+        let step = Step {
+            stack: &self.stack,
+            instruction: instr,
+            word,
+            depth: self.returns.len(),
+        };
+        match &mut self.tracer {
+            Some(tracer) => tracer(&step),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use forth::{Error, Forth, Value};
+use forth::{repl, Arithmetic, Diagnostic, Error, Forth, Limits, Optimization, Step, Value};
 
 #[test]
 fn no_input_no_stack() {
@@ -327,3 +327,1246 @@ fn redefine_an_existing_word_with_another_existing_word() {
     assert!(f.eval("bar foo").is_ok());
     assert_eq!(vec![6, 6], f.stack());
 }
+
+// Control flow
+
+#[test]
+fn if_then_at_top_level() {
+    let mut f = Forth::new();
+    assert!(f.eval("1 if 10 then 0 if 20 then").is_ok());
+    assert_eq!(vec![10], f.stack());
+}
+
+#[test]
+fn if_else_then_in_definition() {
+    let mut f = Forth::new();
+    assert!(f.eval(": pick-one if 1 else 2 then ;").is_ok());
+    assert!(f.eval("5 pick-one 0 pick-one").is_ok());
+    assert_eq!(vec![1, 2], f.stack());
+}
+
+#[test]
+fn nested_if() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": both if if 3 else 2 then else drop 1 then ;")
+        .is_ok());
+    assert!(f.eval("1 1 both 0 1 both 1 0 both").is_ok());
+    assert_eq!(vec![3, 2, 1], f.stack());
+}
+
+#[test]
+fn do_loop_with_index() {
+    let mut f = Forth::new();
+    assert!(f.eval("4 0 do i loop").is_ok());
+    assert_eq!(vec![0, 1, 2, 3], f.stack());
+}
+
+#[test]
+fn nested_do_loop_with_outer_index() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": grid 2 0 do 2 0 do j 10 * i + loop loop ;")
+        .is_ok());
+    assert!(f.eval("grid").is_ok());
+    assert_eq!(vec![0, 1, 10, 11], f.stack());
+}
+
+#[test]
+fn begin_until() {
+    let mut f = Forth::new();
+    assert!(f.eval(": skip-zeros begin until ;").is_ok());
+    assert!(f.eval("1 0 0 skip-zeros 9").is_ok());
+    assert_eq!(vec![9], f.stack());
+}
+
+#[test]
+fn begin_while_repeat() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval("0 3 begin dup while swap 1 + swap 1 - repeat drop")
+        .is_ok());
+    assert_eq!(vec![3], f.stack());
+}
+
+#[test]
+fn loop_index_outside_loop() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::StackUnderflow), f.eval("i"));
+}
+
+#[test]
+fn unbalanced_control_flow() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::InvalidWord), f.eval("1 if 2"));
+    assert_eq!(Err(Error::InvalidWord), f.eval("then"));
+    assert_eq!(Err(Error::InvalidWord), f.eval(": foo then ;"));
+    assert_eq!(Err(Error::InvalidWord), f.eval(": foo 1 if 2 ;"));
+    assert_eq!(Err(Error::InvalidWord), f.eval(": foo begin loop ;"));
+}
+
+#[test]
+fn user_definitions_shadow_control_words() {
+    let mut f = Forth::new();
+    assert!(f.eval(": i 42 ; i").is_ok());
+    assert!(f.eval(": then 1 + ; 1 then").is_ok());
+    assert!(f.eval(": recurse 2 ; recurse").is_ok());
+    assert_eq!(vec![42, 2, 2], f.stack());
+    assert_eq!(Err(Error::InvalidWord), f.eval(": ; 1 ;"));
+}
+
+// Comparison, logic and extended arithmetic
+
+#[test]
+fn comparisons_push_forth_truth_values() {
+    let mut f = Forth::new();
+    assert!(f.eval("1 1 = 1 2 = 1 2 < 2 1 < 2 1 > 0 0= 5 0=").is_ok());
+    assert_eq!(vec![-1, 0, -1, 0, -1, -1, 0], f.stack());
+}
+
+#[test]
+fn bitwise_logic() {
+    let mut f = Forth::new();
+    assert!(f.eval("12 10 and 12 10 or 12 10 xor 0 invert").is_ok());
+    assert_eq!(vec![8, 14, 6, -1], f.stack());
+}
+
+#[test]
+fn extended_arithmetic() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval("7 3 mod 7 3 /mod 5 negate -4 abs 3 8 min 3 8 max")
+        .is_ok());
+    assert_eq!(vec![1, 1, 2, -5, 4, 3, 8], f.stack());
+}
+
+#[test]
+fn modulo_by_zero() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::DivisionByZero), f.eval("4 0 mod"));
+    assert_eq!(Err(Error::DivisionByZero), f.eval("4 0 /mod"));
+}
+
+#[test]
+fn comparison_error() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::StackUnderflow), f.eval("1 ="));
+    assert_eq!(Err(Error::StackUnderflow), f.eval("0="));
+}
+
+#[test]
+fn comparisons_drive_control_flow() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": countdown begin 1 - dup dup 0= until drop ;")
+        .is_ok());
+    assert!(f.eval("3 countdown").is_ok());
+    assert_eq!(vec![2, 1, 0], f.stack());
+}
+
+// Overflow
+
+#[test]
+fn overflow_is_an_error_by_default() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::Overflow), f.eval("2147483647 1 +"));
+    assert_eq!(Err(Error::Overflow), f.eval("-2147483648 1 -"));
+    assert_eq!(Err(Error::Overflow), f.eval("65536 65536 *"));
+    assert_eq!(Err(Error::Overflow), f.eval("-2147483648 negate"));
+    assert_eq!(Err(Error::Overflow), f.eval("-2147483648 abs"));
+}
+
+#[test]
+fn dividing_min_by_negative_one_overflows() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::Overflow), f.eval("-2147483648 -1 /"));
+    assert_eq!(Err(Error::Overflow), f.eval("-2147483648 -1 mod"));
+    assert_eq!(Err(Error::Overflow), f.eval("-2147483648 -1 /mod"));
+}
+
+#[test]
+fn wrapping_arithmetic_is_opt_in() {
+    let mut f = Forth::new().with_arithmetic(Arithmetic::Wrapping);
+    assert!(f
+        .eval("2147483647 1 + -2147483648 -1 / -2147483648 negate")
+        .is_ok());
+    assert_eq!(vec![i32::MIN, i32::MIN, i32::MIN], f.stack());
+}
+
+#[test]
+fn wrapping_arithmetic_still_rejects_division_by_zero() {
+    let mut f = Forth::new().with_arithmetic(Arithmetic::Wrapping);
+    assert_eq!(Err(Error::DivisionByZero), f.eval("1 0 /"));
+}
+
+// Execution limits
+
+#[test]
+fn step_limit_stops_infinite_loops() {
+    let limits = Limits {
+        max_steps: 1000,
+        ..Limits::default()
+    };
+    let mut f = Forth::new().with_limits(limits);
+    assert_eq!(Err(Error::StepLimitExceeded), f.eval("begin 0 until"));
+}
+
+#[test]
+fn step_limit_is_per_eval() {
+    let limits = Limits {
+        max_steps: 3,
+        ..Limits::default()
+    };
+    let mut f = Forth::new().with_limits(limits);
+    assert!(f.eval("1 2 3").is_ok());
+    assert!(f.eval("4 5 6").is_ok());
+    assert_eq!(Err(Error::StepLimitExceeded), f.eval("7 8 9 10"));
+}
+
+#[test]
+fn stack_depth_limit() {
+    let limits = Limits {
+        max_stack_depth: 2,
+        ..Limits::default()
+    };
+    let mut f = Forth::new().with_limits(limits);
+    assert!(f.eval("1 dup").is_ok());
+    assert_eq!(Err(Error::StackOverflow), f.eval("dup"));
+    assert_eq!(vec![1, 1], f.stack());
+}
+
+#[test]
+fn call_depth_limit() {
+    let limits = Limits {
+        max_call_depth: 2,
+        ..Limits::default()
+    };
+    let mut f = Forth::new().with_limits(limits);
+    assert!(f.eval(": a 1 ; : b a ; : c b ;").is_ok());
+    assert!(f.eval("b").is_ok());
+    assert_eq!(Err(Error::CallDepthExceeded), f.eval("c"));
+}
+
+#[test]
+fn definition_limit_counts_shadowed_words() {
+    let limits = Limits {
+        max_definitions: 2,
+        ..Limits::default()
+    };
+    let mut f = Forth::new().with_limits(limits);
+    assert!(f.eval(": foo 1 ; : foo 2 ;").is_ok());
+    assert_eq!(Err(Error::TooManyDefinitions), f.eval(": foo 3 ;"));
+    assert!(f.eval("foo").is_ok());
+    assert_eq!(vec![2], f.stack());
+}
+
+#[test]
+fn words_lists_visible_definitions_newest_first() {
+    let mut f = Forth::new();
+    assert!(f.eval(": foo 1 ; : bar 2 ; : foo 3 ;").is_ok());
+    assert_eq!(vec!["foo", "bar"], f.words());
+}
+
+// Output
+
+fn output_of(f: &Forth) -> String {
+    String::from_utf8(f.output().to_vec()).unwrap()
+}
+
+#[test]
+fn dot_prints_and_pops() {
+    let mut f = Forth::new();
+    assert!(f.eval("1 2 . .").is_ok());
+    assert_eq!("2 1 ", output_of(&f));
+    assert!(f.stack().is_empty());
+}
+
+#[test]
+fn emit_and_cr() {
+    let mut f = Forth::new();
+    assert!(f.eval("72 emit 105 emit cr").is_ok());
+    assert_eq!("Hi\n", output_of(&f));
+}
+
+#[test]
+fn print_string_literal() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": greet .\" Hello, World!\" ; greet cr greet")
+        .is_ok());
+    assert_eq!("Hello, World!\nHello, World!", output_of(&f));
+}
+
+#[test]
+fn print_stack_leaves_it_unchanged() {
+    let mut f = Forth::new();
+    assert!(f.eval("1 2 3 .s").is_ok());
+    assert_eq!("<3> 1 2 3 ", output_of(&f));
+    assert_eq!(vec![1, 2, 3], f.stack());
+}
+
+#[test]
+fn unterminated_string_literal() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::InvalidWord), f.eval(".\" oops"));
+}
+
+#[test]
+fn take_output_empties_the_buffer() {
+    let mut f = Forth::new();
+    assert!(f.eval("4 3 do i . loop").is_ok());
+    assert_eq!(b"3 ".to_vec(), f.take_output());
+    assert!(f.output().is_empty());
+}
+
+#[test]
+fn output_can_go_to_a_sink() {
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let sink = Shared::default();
+    let mut f = Forth::new().with_output(sink.clone());
+    assert!(f.eval("42 .").is_ok());
+    assert_eq!(b"42 ".to_vec(), *sink.0.borrow());
+    assert!(f.output().is_empty());
+}
+
+// Diagnostics
+
+#[test]
+fn diagnostic_points_at_the_offending_word() {
+    let mut f = Forth::new();
+    assert_eq!(
+        Err(Diagnostic {
+            error: Error::UnknownWord,
+            word: "foo".to_string(),
+            offset: 12,
+            line: 2,
+            call_stack: vec![],
+        }),
+        f.eval_diagnostic("1 2 +\n  3 * foo 4")
+    );
+}
+
+#[test]
+fn diagnostic_includes_the_call_stack() {
+    let mut f = Forth::new();
+    assert!(f.eval(": inner 0 / ; : outer 1 inner ;").is_ok());
+    let err = f.eval_diagnostic("5 outer").unwrap_err();
+    assert_eq!(Error::DivisionByZero, err.error);
+    assert_eq!("outer", err.word);
+    assert_eq!(vec!["outer", "inner"], err.call_stack);
+    assert_eq!(
+        "division by zero at `outer` (line 1, byte 2) in outer > inner",
+        err.to_string()
+    );
+}
+
+#[test]
+fn call_stack_is_cleared_between_evals() {
+    let mut f = Forth::new();
+    assert!(f.eval(": boom drop ;").is_ok());
+    assert!(f.eval_diagnostic("boom").is_err());
+    let err = f.eval_diagnostic("+").unwrap_err();
+    assert!(err.call_stack.is_empty());
+}
+
+#[test]
+fn errors_display_as_text() {
+    assert_eq!("stack underflow", Error::StackUnderflow.to_string());
+}
+
+// Atomic evaluation
+
+#[test]
+fn atomic_eval_restores_stack_and_definitions_on_error() {
+    let mut f = Forth::new();
+    assert!(f.eval(": foo 1 ; 10 20").is_ok());
+    assert_eq!(
+        Err(Error::UnknownWord),
+        f.eval_atomic(": foo 2 ; : bar 3 ; drop drop 4 5 baz")
+    );
+    assert_eq!(vec![10, 20], f.stack());
+    assert_eq!(vec!["foo"], f.words());
+    assert!(f.eval("foo").is_ok());
+    assert_eq!(vec![10, 20, 1], f.stack());
+}
+
+#[test]
+fn atomic_eval_keeps_changes_on_success() {
+    let mut f = Forth::new();
+    assert!(f.eval_atomic(": foo 1 ; foo foo +").is_ok());
+    assert_eq!(vec![2], f.stack());
+    assert_eq!(vec!["foo"], f.words());
+}
+
+#[test]
+fn plain_eval_keeps_partial_changes() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::DivisionByZero), f.eval("1 2 : foo ; 0 /"));
+    assert_eq!(vec![1], f.stack());
+    assert_eq!(vec!["foo"], f.words());
+}
+
+// Variables and constants
+
+#[test]
+fn variables_store_and_fetch() {
+    let mut f = Forth::new();
+    assert!(f.eval("variable x 42 x ! x @").is_ok());
+    assert!(f.eval("x @ 1 + x ! x @").is_ok());
+    assert_eq!(vec![42, 43], f.stack());
+}
+
+#[test]
+fn variables_start_at_zero_and_can_be_incremented() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval("variable count : tick 1 count +! ; tick tick tick count @")
+        .is_ok());
+    assert_eq!(vec![3], f.stack());
+}
+
+#[test]
+fn constants() {
+    let mut f = Forth::new();
+    assert!(f.eval("10 constant ten ten ten *").is_ok());
+    assert_eq!(vec![100], f.stack());
+    assert_eq!(Err(Error::StackUnderflow), f.eval("drop constant nothing"));
+}
+
+#[test]
+fn redefined_variable_keeps_old_binding_in_existing_words() {
+    let mut f = Forth::new();
+    assert!(f.eval("variable x 1 x ! : old-x x @ ;").is_ok());
+    assert!(f.eval("variable x 2 x ! old-x x @").is_ok());
+    assert_eq!(vec![1, 2], f.stack());
+}
+
+#[test]
+fn allot_reserves_cells_after_a_variable() {
+    let mut f = Forth::new();
+    assert!(f.eval("variable arr 2 allot 7 arr 2 + ! arr 2 + @").is_ok());
+    assert_eq!(vec![7], f.stack());
+}
+
+#[test]
+fn invalid_addresses() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::InvalidAddress), f.eval("0 @"));
+    assert!(f.eval("variable x").is_ok());
+    assert_eq!(Err(Error::InvalidAddress), f.eval("1 x 1 + !"));
+    assert_eq!(Err(Error::InvalidAddress), f.eval("-1 @"));
+}
+
+#[test]
+fn memory_is_bounded() {
+    let limits = Limits {
+        max_cells: 4,
+        ..Limits::default()
+    };
+    let mut f = Forth::new().with_limits(limits);
+    assert!(f.eval("variable x 3 allot").is_ok());
+    assert_eq!(Err(Error::OutOfMemory), f.eval("variable y"));
+    assert_eq!(Err(Error::OutOfMemory), f.eval("1 allot"));
+}
+
+#[test]
+fn oversized_allot_fails_without_limits() {
+    let mut f = Forth::<i64>::default();
+    assert_eq!(
+        Err(Error::OutOfMemory),
+        f.eval("variable x 100000000000000000 allot")
+    );
+    assert!(f.eval("5 x ! x @").is_ok());
+    assert_eq!(vec![5], f.stack());
+}
+
+#[test]
+fn variable_cannot_be_used_inside_a_definition() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::InvalidWord), f.eval(": foo variable x ;"));
+    assert_eq!(Err(Error::InvalidWord), f.eval("variable 1"));
+}
+
+#[test]
+fn cannot_redefine_variable_or_constant() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::InvalidWord), f.eval(": variable 1 ;"));
+    assert_eq!(Err(Error::InvalidWord), f.eval(": constant 1 ;"));
+    assert_eq!(Err(Error::InvalidWord), f.eval("variable constant"));
+    assert!(f.words().is_empty());
+}
+
+#[test]
+fn atomic_eval_restores_memory() {
+    let mut f = Forth::new();
+    assert!(f.eval("variable x 5 x !").is_ok());
+    assert!(f.eval_atomic("9 x ! oops").is_err());
+    assert!(f.eval("x @").is_ok());
+    assert_eq!(vec![5], f.stack());
+}
+
+// Native words
+
+#[test]
+fn native_words_can_be_called() {
+    let mut f = Forth::new();
+    assert!(f
+        .register("answer", |stack| {
+            stack.push(42);
+            Ok(())
+        })
+        .is_ok());
+    assert!(f.eval("answer ANSWER").is_ok());
+    assert_eq!(vec![42, 42], f.stack());
+}
+
+#[test]
+fn native_words_can_be_used_in_definitions_and_shadowed() {
+    let mut f = Forth::new();
+    assert!(f
+        .register("double", |stack| {
+            let x = stack.pop().ok_or(Error::StackUnderflow)?;
+            stack.push(x * 2);
+            Ok(())
+        })
+        .is_ok());
+    assert!(f.eval(": quad double double ;").is_ok());
+    assert!(f.eval(": double 0 ;").is_ok());
+    assert!(f.eval("3 quad double").is_ok());
+    assert_eq!(vec![12, 0], f.stack());
+}
+
+#[test]
+fn native_words_keep_state_and_report_errors() {
+    let mut calls = 0;
+    let mut f = Forth::new();
+    assert!(f
+        .register("counter", move |stack| {
+            calls += 1;
+            stack.push(calls);
+            Ok(())
+        })
+        .is_ok());
+    assert!(f.register("fail", |_| Err(Error::InvalidWord)).is_ok());
+    assert!(f.eval("counter counter").is_ok());
+    assert_eq!(vec![1, 2], f.stack());
+    assert_eq!(Err(Error::InvalidWord), f.eval("fail"));
+}
+
+#[test]
+fn native_words_must_have_a_valid_name() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::InvalidWord), f.register("12", |_| Ok(())));
+}
+
+// Optimization
+
+#[test]
+fn constant_arithmetic_is_folded() {
+    let limits = Limits {
+        max_steps: 1,
+        ..Limits::default()
+    };
+    let mut f = Forth::new()
+        .with_optimization(Optimization::Full)
+        .with_limits(limits);
+    assert!(f.eval(": f 2 3 + 4 * negate ;").is_ok());
+    assert!(f.eval("f").is_ok());
+    assert_eq!(vec![-20], f.stack());
+}
+
+#[test]
+fn small_definitions_are_inlined() {
+    let limits = Limits {
+        max_call_depth: 0,
+        ..Limits::default()
+    };
+    let mut f = Forth::new()
+        .with_optimization(Optimization::Full)
+        .with_limits(limits);
+    assert!(f.eval(": sq dup * ; : neg-sq sq negate ;").is_ok());
+    assert!(f.eval("3 neg-sq 2 sq").is_ok());
+    assert_eq!(vec![-9, 4], f.stack());
+}
+
+#[test]
+fn folding_leaves_failures_until_run_time() {
+    let mut f = Forth::new().with_optimization(Optimization::Full);
+    assert!(f.eval(": f 1 0 / ;").is_ok());
+    assert_eq!(Err(Error::DivisionByZero), f.eval("f"));
+
+    let mut f = Forth::new()
+        .with_optimization(Optimization::Full)
+        .with_arithmetic(Arithmetic::Wrapping);
+    assert!(f.eval(": g 2147483647 1 + ;").is_ok());
+    assert!(f.eval("g").is_ok());
+    assert_eq!(vec![i32::MIN], f.stack());
+}
+
+#[test]
+fn jump_targets_are_not_folded_away() {
+    let mut f = Forth::new().with_optimization(Optimization::Full);
+    assert!(f.eval(": f if 1 else 2 then 10 + ;").is_ok());
+    assert!(f.eval("-1 f 0 f").is_ok());
+    assert_eq!(vec![11, 12], f.stack());
+}
+
+#[test]
+fn inlined_words_keep_their_definition_time_meaning() {
+    let mut f = Forth::new().with_optimization(Optimization::Full);
+    assert!(f.eval(": one 1 ; : two one one + ; : one 100 ;").is_ok());
+    assert!(f.eval("two one").is_ok());
+    assert_eq!(vec![2, 100], f.stack());
+}
+
+#[test]
+fn optimized_loops_and_recursion_still_run() {
+    let mut f = Forth::new().with_optimization(Optimization::Full);
+    assert!(f.eval(": sum 0 swap 0 do i 1 2 + * + loop ; 4 sum").is_ok());
+    assert!(f
+        .eval(": fact dup 1 > if dup 1 - recurse * then ; 5 fact")
+        .is_ok());
+    assert!(f.eval(": count begin 1 - dup 0= until ; 3 count").is_ok());
+    assert_eq!(vec![18, 120, 0], f.stack());
+}
+
+#[test]
+fn unoptimized_words_keep_every_call() {
+    let mut f = Forth::new();
+    assert!(f.eval(": foo 1 0 / ; : bar foo foo foo foo ;").is_ok());
+    let err = f.eval_diagnostic("bar").unwrap_err();
+    assert_eq!(vec!["bar", "foo"], err.call_stack);
+
+    let limits = Limits {
+        max_call_depth: 1,
+        ..Limits::default()
+    };
+    let mut f = Forth::new().with_limits(limits);
+    assert!(f.eval(": sq dup * ; : big sq sq ;").is_ok());
+    assert_eq!(Err(Error::CallDepthExceeded), f.eval("2 big"));
+}
+
+#[test]
+fn unoptimized_words_are_traced_call_by_call() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let words = Rc::new(RefCell::new(vec![]));
+    let seen = words.clone();
+    let mut f = Forth::new().with_tracer(move |step: &Step| {
+        seen.borrow_mut().push(step.word.clone());
+        Ok(())
+    });
+    assert!(f.eval(": sq dup * ; : big sq sq sq sq ; 1 big").is_ok());
+    assert_eq!(4, words.borrow().iter().filter(|w| *w == "sq").count());
+}
+
+#[test]
+fn optimized_statements_point_at_the_failing_word() {
+    let mut f = Forth::new().with_optimization(Optimization::Full);
+    let err = f.eval_diagnostic("2 3 + 1 0 / drop").unwrap_err();
+    assert_eq!(Error::DivisionByZero, err.error);
+    assert_eq!(("/", 10), (err.word.as_str(), err.offset));
+}
+
+// Cell types
+
+#[test]
+fn wider_cells_hold_larger_numbers() {
+    let mut f = Forth::<i64>::default();
+    assert!(f.eval("3000000000 2 * dup .").is_ok());
+    assert_eq!(vec![6_000_000_000i64], f.stack());
+    assert_eq!(b"6000000000 ".to_vec(), f.output());
+}
+
+#[test]
+fn overflow_is_checked_per_cell_type() {
+    let mut f = Forth::<i64>::default();
+    assert_eq!(Err(Error::Overflow), f.eval("9223372036854775807 1 +"));
+
+    let mut f = Forth::<i128>::default().with_arithmetic(Arithmetic::Wrapping);
+    assert!(f
+        .eval("170141183460469231731687303715884105727 1 +")
+        .is_ok());
+    assert_eq!(vec![i128::MIN], f.stack());
+}
+
+#[test]
+fn numbers_are_parsed_for_the_cell_type() {
+    let mut f = Forth::<i64>::default();
+    assert_eq!(Err(Error::InvalidWord), f.eval(": 3000000000 1 ;"));
+
+    let mut f = Forth::new();
+    assert!(f.eval(": 3000000000 1 ; 3000000000").is_ok());
+    assert_eq!(vec![1], f.stack());
+}
+
+#[test]
+fn wider_cells_work_with_memory_and_loops() {
+    let mut f = Forth::<i64>::default();
+    assert!(f
+        .eval("variable total 5 0 do 1000000000 total +! loop total @")
+        .is_ok());
+    assert_eq!(vec![5_000_000_000i64], f.stack());
+}
+
+#[cfg(feature = "bigint")]
+#[test]
+fn big_integer_cells_never_overflow() {
+    use num_bigint::BigInt;
+
+    let mut f = Forth::<BigInt>::default();
+    assert!(f.eval(": sq dup * ; 4294967296 sq sq").is_ok());
+    let expected: BigInt = "340282366920938463463374607431768211456".parse().unwrap();
+    assert_eq!(vec![expected], f.stack());
+}
+
+// Sessions
+
+#[test]
+fn sessions_round_trip() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": foo 5 ; : bar foo ; : foo 6 ; variable x 7 x ! 1 2")
+        .is_ok());
+    assert!(f
+        .eval(": count-up 0 do i . loop .\" done\" cr ; : pick-one if bar else foo then ;")
+        .is_ok());
+    let saved = f.save_session();
+
+    let mut g = Forth::new();
+    assert!(g.restore_session(&saved).is_ok());
+    assert_eq!(vec![1, 2], g.stack());
+    assert_eq!(f.words(), g.words());
+    assert_eq!(saved, g.save_session());
+    assert!(g.eval("-1 pick-one 0 pick-one x @ 3 count-up").is_ok());
+    assert_eq!(vec![1, 2, 5, 6, 7], g.stack());
+    assert_eq!(b"0 1 2 done\n".to_vec(), g.output());
+}
+
+#[test]
+fn sessions_keep_control_characters_in_strings() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": x .\" a\rb\tc\\d\u{7}\u{0}\" ; : y .\" \\n\\u{7}\" ;")
+        .is_ok());
+    let saved = f.save_session();
+    assert!(!saved.contains('\r'));
+
+    let mut g = Forth::new();
+    assert!(g.restore_session(&saved).is_ok());
+    assert!(g.eval("x y").is_ok());
+    assert_eq!(b"a\rb\tc\\d\x07\x00\\n\\u{7}".to_vec(), g.output());
+}
+
+#[test]
+fn optimized_sessions_are_saved_as_written() {
+    let mut f = Forth::new().with_optimization(Optimization::Full);
+    assert!(f.eval(": sq dup * ; : quad sq sq 1 2 + * ;").is_ok());
+    let mut plain = Forth::new();
+    assert!(plain.eval(": sq dup * ; : quad sq sq 1 2 + * ;").is_ok());
+    let saved = f.save_session();
+    assert_eq!(plain.save_session(), saved);
+
+    let limits = Limits {
+        max_call_depth: 1,
+        ..Limits::default()
+    };
+    let mut g = Forth::new()
+        .with_optimization(Optimization::Full)
+        .with_limits(limits);
+    assert!(g.restore_session(&saved).is_ok());
+    assert!(g.eval("2 quad").is_ok());
+    assert_eq!(vec![48], g.stack());
+    assert_eq!(
+        Ok(": quad sq sq 1 2 + * ;".to_string()),
+        g.decompile("quad")
+    );
+}
+
+#[test]
+fn restored_sessions_can_be_extended() {
+    let mut f = Forth::new();
+    assert!(f.eval(": sq dup * ; : quad sq sq 0 + ;").is_ok());
+    let mut g = Forth::new();
+    assert!(g.restore_session(&f.save_session()).is_ok());
+    assert!(g.eval(": sq 0 ; 2 quad sq").is_ok());
+    assert_eq!(vec![16, 0], g.stack());
+}
+
+#[test]
+fn sessions_keep_native_words_by_registration_order() {
+    let mut f = Forth::new();
+    assert!(f
+        .register("seven", |stack| {
+            stack.push(7);
+            Ok(())
+        })
+        .is_ok());
+    assert!(f.eval(": twice-seven seven seven + ;").is_ok());
+    let saved = f.save_session();
+
+    assert_eq!(
+        Err(Error::InvalidSession),
+        Forth::new().restore_session(&saved)
+    );
+
+    let mut g = Forth::new();
+    assert!(g
+        .register("seven", |stack| {
+            stack.push(7);
+            Ok(())
+        })
+        .is_ok());
+    assert!(g.restore_session(&saved).is_ok());
+    assert!(g.eval("twice-seven").is_ok());
+    assert_eq!(vec![14], g.stack());
+}
+
+#[test]
+fn invalid_sessions_are_rejected_without_changes() {
+    let mut f = Forth::new();
+    assert!(f.eval(": foo 1 ; 9").is_ok());
+    for bad in [
+        "",
+        "not a session",
+        "forth-session 1\nstack x\nmemory\n",
+        "forth-session 1\nstack\nmemory\nlit 1\n",
+        "forth-session 1\nstack\nmemory\nword foo\nlit 1\n",
+        "forth-session 1\nstack\nmemory\nword foo\ncall 5\nreturn\n",
+        "forth-session 1\nstack\nmemory\nword foo\ncall 1\nreturn\nword bar\ncall 0\nreturn\n",
+        "forth-session 1\nstack\nmemory\nword foo\nbranch 9\nreturn\n",
+        "forth-session 1\nstack\nmemory\nword foo\nfrobnicate\nreturn\n",
+    ] {
+        assert_eq!(Err(Error::InvalidSession), f.restore_session(bad));
+    }
+    assert!(f.eval("foo").is_ok());
+    assert_eq!(vec![9, 1], f.stack());
+}
+
+#[test]
+fn sessions_only_call_earlier_definitions_or_themselves() {
+    let mut f = Forth::new().with_optimization(Optimization::Full);
+    let forward =
+        "forth-session 1\nstack\nmemory\nword foo\ncall 1\nreturn\nword bar\ncall 0\nreturn\n";
+    assert_eq!(Err(Error::InvalidSession), f.restore_session(forward));
+
+    let recursive =
+        "forth-session 1\nstack\nmemory\nword foo\ncall 0\nreturn\nword bar\ncall 0\nreturn\n";
+    assert!(f.restore_session(recursive).is_ok());
+    assert_eq!(vec!["bar", "foo"], f.words());
+}
+
+// Decompiling
+
+#[test]
+fn decompile_renders_control_structures() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": sign dup 0 < if drop -1 else 0 > if 1 else 0 then then ;")
+        .is_ok());
+    assert!(f.eval(": count 0 do i . loop ;").is_ok());
+    assert!(f
+        .eval(": halve begin dup 1 > while 2 / repeat begin 1 - dup 0= until ;")
+        .is_ok());
+    assert!(f.eval(": greet .\" hello world\" cr ;").is_ok());
+    assert_eq!(
+        Ok(": sign dup 0 < if drop -1 else 0 > if 1 else 0 then then ;".to_string()),
+        f.decompile("sign")
+    );
+    assert_eq!(
+        Ok(": count 0 do i . loop ;".to_string()),
+        f.decompile("COUNT")
+    );
+    assert_eq!(
+        Ok(": halve begin dup 1 > while 2 / repeat begin 1 - dup 0= until ;".to_string()),
+        f.decompile("halve")
+    );
+    assert_eq!(
+        Ok(": greet .\" hello world\" cr ;".to_string()),
+        f.decompile("greet")
+    );
+}
+
+#[test]
+fn decompile_shows_the_code_as_written_when_optimized() {
+    let mut f = Forth::new().with_optimization(Optimization::Full);
+    assert!(f.eval(": sq dup * ; : area sq 2 3 * * ;").is_ok());
+    assert_eq!(Ok(": area sq 2 3 * * ;".to_string()), f.decompile("area"));
+
+    assert!(f.eval(": foo 5 ; : bar foo ; : foo 6 ;").is_ok());
+    assert_eq!(
+        Ok(": bar foo ( shadowed ) ;".to_string()),
+        f.decompile("bar")
+    );
+}
+
+#[test]
+fn decompile_marks_calls_to_shadowed_definitions() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": inner 1 2 3 4 ; : outer inner inner ; : inner 5 ;")
+        .is_ok());
+    assert_eq!(
+        Ok(": outer inner ( shadowed ) inner ( shadowed ) ;".to_string()),
+        f.decompile("outer")
+    );
+    assert_eq!(Ok(": inner 5 ;".to_string()), f.decompile("inner"));
+    assert_eq!(Err(Error::UnknownWord), f.decompile("missing"));
+}
+
+#[test]
+fn see_writes_the_source() {
+    let mut f = Forth::new();
+    assert!(f.eval(": foo 1 + ; see foo").is_ok());
+    assert_eq!(b": foo 1 + ;\n".to_vec(), f.output());
+    assert_eq!(Err(Error::UnknownWord), f.eval("see dup"));
+    assert_eq!(Err(Error::InvalidWord), f.eval("see"));
+    assert_eq!(Err(Error::InvalidWord), f.eval(": bar see foo ;"));
+    assert_eq!(Err(Error::InvalidWord), f.eval(": see 1 ;"));
+}
+
+#[test]
+fn see_marks_calls_to_shadowed_definitions() {
+    let mut f = Forth::new();
+    assert!(f.eval(": foo 5 ; : bar foo ; : foo 6 ; see bar").is_ok());
+    assert_eq!(b": bar foo ( shadowed ) ;\n".to_vec(), f.output());
+}
+
+#[test]
+fn see_shows_registered_words_as_native() {
+    let mut f = Forth::new();
+    assert!(f
+        .register("answer", |stack| {
+            stack.push(42);
+            Ok(())
+        })
+        .is_ok());
+    assert!(f
+        .eval(": twice answer answer ; see answer see twice")
+        .is_ok());
+    assert_eq!(
+        b": answer ( native 0 ) ;\n: twice answer answer ;\n".to_vec(),
+        f.output()
+    );
+}
+
+// Tracing
+
+#[test]
+fn tracer_sees_each_instruction_before_it_runs() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let steps = Rc::new(RefCell::new(vec![]));
+    let seen = steps.clone();
+    let mut f = Forth::new().with_tracer(move |step: &Step| {
+        seen.borrow_mut()
+            .push((step.word.clone(), step.stack.to_vec(), step.depth));
+        Ok(())
+    });
+    assert!(f.eval(": scale 2 * 1 + ; 3 scale").is_ok());
+    assert_eq!(vec![7], f.stack());
+    assert_eq!(
+        vec![
+            ("3".to_string(), vec![], 0),
+            ("scale".to_string(), vec![3], 0),
+            ("2".to_string(), vec![3], 1),
+            ("*".to_string(), vec![3, 2], 1),
+            ("1".to_string(), vec![6], 1),
+            ("+".to_string(), vec![6, 1], 1),
+            ("exit".to_string(), vec![7], 1),
+        ],
+        *steps.borrow()
+    );
+}
+
+#[test]
+fn tracer_errors_stop_the_program() {
+    let mut f = Forth::new().with_tracer(|step: &Step| {
+        if step.word == "." {
+            return Err(Error::InvalidWord);
+        }
+        Ok(())
+    });
+    assert_eq!(Err(Error::InvalidWord), f.eval("1 2 . 3"));
+    assert_eq!(vec![1, 2], f.stack());
+}
+
+#[test]
+fn trace_steps_display_as_readable_lines() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let lines = Rc::new(RefCell::new(vec![]));
+    let seen = lines.clone();
+    let mut f = Forth::new().with_tracer(move |step: &Step| {
+        seen.borrow_mut().push(step.to_string());
+        Ok(())
+    });
+    assert!(f.eval(": pos? 0 > if 1 else 0 then drop ; 5 pos?").is_ok());
+    assert_eq!(
+        vec![
+            "5            <0>",
+            "pos?         <1> 5",
+            "  0          <1> 5",
+            "  >          <2> 5 0",
+            "  0branch    <1> -1",
+            "  1          <0>",
+            "  branch     <1> 1",
+            "  drop       <1> 1",
+            "  exit       <0>",
+        ],
+        *lines.borrow()
+    );
+}
+
+// Return stack and recursion
+
+#[test]
+fn return_stack_words() {
+    let mut f = Forth::new();
+    assert!(f.eval(": tuck-sum >r dup r@ + r> ; 1 2 tuck-sum").is_ok());
+    assert_eq!(vec![1, 3, 2], f.stack());
+}
+
+#[test]
+fn return_stack_underflow() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::StackUnderflow), f.eval("r>"));
+    assert_eq!(Err(Error::StackUnderflow), f.eval("r@"));
+    assert_eq!(Err(Error::StackUnderflow), f.eval(">r"));
+}
+
+#[test]
+fn words_cannot_reach_their_callers_return_values() {
+    let mut f = Forth::new();
+    assert!(f.eval(": peek r@ ; : outer 1 >r peek r> ;").is_ok());
+    assert_eq!(Err(Error::StackUnderflow), f.eval("outer"));
+}
+
+#[test]
+fn return_values_are_dropped_when_a_word_returns() {
+    let mut f = Forth::new();
+    assert!(f.eval(": stash 1 2 3 >r ; : outer stash r> ;").is_ok());
+    assert_eq!(Err(Error::StackUnderflow), f.eval("outer"));
+}
+
+#[test]
+fn recurse_calls_the_word_being_defined() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": fact dup 1 > if dup 1 - recurse * then ; 5 fact")
+        .is_ok());
+    assert_eq!(vec![120], f.stack());
+}
+
+#[test]
+fn recurse_refers_to_the_new_definition_not_an_old_one() {
+    let mut f = Forth::new();
+    assert!(f.eval(": countdown 99 ;").is_ok());
+    assert!(f
+        .eval(": countdown dup 0 > if dup 1 - recurse then ; 3 countdown")
+        .is_ok());
+    assert_eq!(vec![3, 2, 1, 0], f.stack());
+    assert_eq!(
+        Ok(": countdown dup 0 > if dup 1 - recurse then ;".to_string()),
+        f.decompile("countdown")
+    );
+}
+
+#[test]
+fn recursion_is_bounded_by_the_call_depth_limit() {
+    let mut f = Forth::new().with_limits(Limits {
+        max_call_depth: 50,
+        ..Limits::default()
+    });
+    assert!(f.eval(": forever recurse ;").is_ok());
+    assert_eq!(Err(Error::CallDepthExceeded), f.eval("forever"));
+}
+
+#[test]
+fn exit_returns_early() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": clamp dup 10 > if drop 10 exit then 1 + ; 3 clamp 20 clamp")
+        .is_ok());
+    assert_eq!(vec![4, 10], f.stack());
+}
+
+#[test]
+fn exit_leaves_loops_and_return_values_behind() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": first-over-2 7 >r 10 0 do i 2 > if i exit then loop 0 ; : run first-over-2 i ;")
+        .is_ok());
+    assert!(f.eval("100 98 do run loop").is_ok());
+    assert_eq!(vec![3, 98, 3, 99], f.stack());
+}
+
+#[test]
+fn recurse_and_exit_only_work_in_definitions() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::InvalidWord), f.eval("recurse"));
+    assert_eq!(Err(Error::InvalidWord), f.eval("exit"));
+}
+
+// Comments and strings
+
+#[test]
+fn parenthesised_comments_are_skipped() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": sq ( n -- n*n ) dup * ; 3 ( three ) sq ( )")
+        .is_ok());
+    assert_eq!(vec![9], f.stack());
+    assert_eq!(Err(Error::UnknownWord), f.eval("(no-space)"));
+}
+
+#[test]
+fn line_comments_run_to_the_end_of_the_line() {
+    let mut f = Forth::new();
+    assert!(f.eval("1 \\ 2 3 ( not closed\n4 \\\n5 \\ trailing").is_ok());
+    assert_eq!(vec![1, 4, 5], f.stack());
+}
+
+#[test]
+fn comments_work_inside_control_structures() {
+    let mut f = Forth::new();
+    assert!(f
+        .eval(": pos? ( n -- f ) 0 > if \\ positive\n1 ( yes ) else 0 then ; 5 pos?")
+        .is_ok());
+    assert_eq!(vec![1], f.stack());
+}
+
+#[test]
+fn unclosed_comment_runs_to_the_end_of_the_input() {
+    let mut f = Forth::new();
+    assert!(f.eval("1 ( 2 3").is_ok());
+    assert_eq!(vec![1], f.stack());
+}
+
+#[test]
+fn string_literals_keep_their_spacing() {
+    let mut f = Forth::new();
+    assert!(f.eval(".\"  two  spaces\" .\" (no comment)\"").is_ok());
+    assert_eq!(" two  spaces(no comment)", output_of(&f));
+}
+
+#[test]
+fn s_quote_pushes_an_address_and_length() {
+    let mut f = Forth::new();
+    assert!(f.eval("s\" hi there\" swap drop").is_ok());
+    assert_eq!(vec![8], f.stack());
+    assert!(f
+        .eval(": greet s\" Hello, \" type type cr ; s\" World!\" greet")
+        .is_ok());
+    assert_eq!("Hello, World!\n", output_of(&f));
+}
+
+#[test]
+fn type_checks_its_range() {
+    let mut f = Forth::new();
+    assert!(f.eval("s\" abc\" drop drop").is_ok());
+    assert_eq!(Err(Error::InvalidAddress), f.eval("0 4 type"));
+    assert_eq!(Err(Error::InvalidAddress), f.eval("-1 2 type"));
+    assert_eq!(Err(Error::StackUnderflow), f.eval("type"));
+}
+
+#[test]
+fn unterminated_s_quote() {
+    let mut f = Forth::new();
+    assert_eq!(Err(Error::InvalidWord), f.eval("s\" oops"));
+}
+
+#[test]
+fn diagnostics_point_past_comments_and_strings() {
+    let mut f = Forth::new();
+    assert_eq!(
+        Err(Diagnostic {
+            error: Error::UnknownWord,
+            word: "oops".to_string(),
+            offset: 22,
+            line: 2,
+            call_stack: vec![],
+        }),
+        f.eval_diagnostic("( a ) .\" b c\" \\ d\n1 2 oops")
+    );
+}
+
+#[test]
+fn diagnostics_point_into_top_level_control_structures() {
+    let mut f = Forth::new();
+    assert_eq!(
+        Err(Diagnostic {
+            error: Error::DivisionByZero,
+            word: "/".to_string(),
+            offset: 11,
+            line: 2,
+            call_stack: vec![],
+        }),
+        f.eval_diagnostic("-1 if\n 1 0 /\n 5 6 7 then")
+    );
+    assert!(f.eval(": foo 0 / ;").is_ok());
+    assert_eq!(
+        Err(Diagnostic {
+            error: Error::DivisionByZero,
+            word: "foo".to_string(),
+            offset: 11,
+            line: 1,
+            call_stack: vec!["foo".to_string()],
+        }),
+        f.eval_diagnostic("3 0 do 1 i foo loop")
+    );
+}
+
+#[test]
+fn repl_answers_each_line() {
+    let mut f = Forth::new();
+    let input = "words\n1 2 .s\n: foo\n  3 ;\nfoo words 0 /\n.\" bye\" 4 bye 5\n6\n";
+    let mut output = vec![];
+    assert!(repl(&mut f, input.as_bytes(), &mut output).is_ok());
+    assert_eq!(
+        "<0> ok\n\
+         <2> 1 2 ok\n\
+         <2> 1 2 ok\n\
+         foo\n\
+         error: division by zero at `/` (line 1, byte 12)\n\
+         bye",
+        String::from_utf8(output).unwrap()
+    );
+    assert_eq!(vec![1, 2, 4], f.stack());
+}
+
+#[test]
+fn repl_follows_user_definitions() {
+    let mut f = Forth::new();
+    let input = ": if 1 ;\nif if\n: words 7 ;\n: foo words ;\nfoo words\n: bar\n  1\n";
+    let mut output = vec![];
+    assert!(repl(&mut f, input.as_bytes(), &mut output).is_ok());
+    assert_eq!(
+        "<0> ok\n\
+         <2> 1 1 ok\n\
+         <2> 1 1 ok\n\
+         <2> 1 1 ok\n\
+         <4> 1 1 7 7 ok\n\
+         error: invalid word at `1` (line 2, byte 8)\n",
+        String::from_utf8(output).unwrap()
+    );
+}
@@ -0,0 +1,62 @@
+//! Compares the lookup-table evaluator with classifying hands by `HandType`.
+//! Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use poker::{best_five, rank_best, rank_five, Card, HandType, Rank, Suit};
+
+/// Hands of `size` different cards, dealt by a fixed pseudo-random sequence.
+fn deal(count: usize, size: usize) -> Vec<Vec<Card>> {
+    let deck: Vec<Card> = Rank::ALL
+        .iter()
+        .flat_map(|&rank| Suit::ALL.iter().map(move |&suit| Card { rank, suit }))
+        .collect();
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    (0..count)
+        .map(|_| {
+            let mut deck = deck.clone();
+            for i in 0..size {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let j = i + (state % (deck.len() - i) as u64) as usize;
+                deck.swap(i, j);
+            }
+            deck.truncate(size);
+            deck
+        })
+        .collect()
+}
+
+fn time(name: &str, hands: &[Vec<Card>], mut evaluate: impl FnMut(&[Card])) {
+    let start = Instant::now();
+    for hand in hands {
+        evaluate(black_box(hand));
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<24} {:>8.1} ns/hand",
+        name,
+        elapsed.as_nanos() as f64 / hands.len() as f64
+    );
+}
+
+fn main() {
+    let fives = deal(200_000, 5);
+    time("HandType::from_cards", &fives, |hand| {
+        black_box(HandType::from_cards(hand).unwrap());
+    });
+    time("rank_five", &fives, |hand| {
+        let hand = [hand[0], hand[1], hand[2], hand[3], hand[4]];
+        black_box(rank_five(&hand));
+    });
+
+    let sevens = deal(20_000, 7);
+    time("best_five of seven", &sevens, |hand| {
+        black_box(best_five(hand));
+    });
+    time("rank_best of seven", &sevens, |hand| {
+        black_box(rank_best(hand));
+    });
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Error;
+
+/// The rank of a card, valued from 2 up to 14 for an ace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rank {
+    Two = 2,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+    Ace,
+}
+
+impl Rank {
+    /// Every rank, lowest first.
+    pub const ALL: [Rank; 13] = [
+        Rank::Two,
+        Rank::Three,
+        Rank::Four,
+        Rank::Five,
+        Rank::Six,
+        Rank::Seven,
+        Rank::Eight,
+        Rank::Nine,
+        Rank::Ten,
+        Rank::Jack,
+        Rank::Queen,
+        Rank::King,
+        Rank::Ace,
+    ];
+
+    pub fn value(self) -> u8 {
+        self as u8
+    }
+
+    /// The rank with the given value, from 2 up to 14 for an ace.
+    pub fn from_value(value: u8) -> Option<Rank> {
+        Rank::ALL.get(usize::from(value.checked_sub(2)?)).copied()
+    }
+
+    /// The rank's name in words, as in "queen".
+    pub fn name(self) -> &'static str {
+        match self {
+            Rank::Two => "two",
+            Rank::Three => "three",
+            Rank::Four => "four",
+            Rank::Five => "five",
+            Rank::Six => "six",
+            Rank::Seven => "seven",
+            Rank::Eight => "eight",
+            Rank::Nine => "nine",
+            Rank::Ten => "ten",
+            Rank::Jack => "jack",
+            Rank::Queen => "queen",
+            Rank::King => "king",
+            Rank::Ace => "ace",
+        }
+    }
+
+    /// The name for several cards of the rank, as in "queens".
+    pub fn plural(self) -> &'static str {
+        match self {
+            Rank::Two => "twos",
+            Rank::Three => "threes",
+            Rank::Four => "fours",
+            Rank::Five => "fives",
+            Rank::Six => "sixes",
+            Rank::Seven => "sevens",
+            Rank::Eight => "eights",
+            Rank::Nine => "nines",
+            Rank::Ten => "tens",
+            Rank::Jack => "jacks",
+            Rank::Queen => "queens",
+            Rank::King => "kings",
+            Rank::Ace => "aces",
+        }
+    }
+}
+
+impl FromStr for Rank {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2" => Ok(Rank::Two),
+            "3" => Ok(Rank::Three),
+            "4" => Ok(Rank::Four),
+            "5" => Ok(Rank::Five),
+            "6" => Ok(Rank::Six),
+            "7" => Ok(Rank::Seven),
+            "8" => Ok(Rank::Eight),
+            "9" => Ok(Rank::Nine),
+            "10" => Ok(Rank::Ten),
+            "J" => Ok(Rank::Jack),
+            "Q" => Ok(Rank::Queen),
+            "K" => Ok(Rank::King),
+            "A" => Ok(Rank::Ace),
+            _ => Err(Error::InvalidRank(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rank::Jack => write!(f, "J"),
+            Rank::Queen => write!(f, "Q"),
+            Rank::King => write!(f, "K"),
+            Rank::Ace => write!(f, "A"),
+            _ => write!(f, "{}", self.value()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl Suit {
+    pub const ALL: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+}
+
+impl FromStr for Suit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "C" => Ok(Suit::Clubs),
+            "D" => Ok(Suit::Diamonds),
+            "H" => Ok(Suit::Hearts),
+            "S" => Ok(Suit::Spades),
+            _ => Err(Error::InvalidSuit(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Suit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            Suit::Clubs => 'C',
+            Suit::Diamonds => 'D',
+            Suit::Hearts => 'H',
+            Suit::Spades => 'S',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+/// A playing card, written as its rank followed by its suit, as in `10H`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Card {
+    pub rank: Rank,
+    pub suit: Suit,
+}
+
+impl FromStr for Card {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s.char_indices().last().map_or(0, |(idx, _)| idx);
+        let (rank, suit) = s.split_at(split);
+        let suit = suit.parse()?;
+        Ok(Card {
+            rank: rank.parse()?,
+            suit,
+        })
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.rank, self.suit)
+    }
+}
+
+/// Every card in a standard deck, ordered by rank and then suit.
+pub(crate) fn full_deck() -> Vec<Card> {
+    Rank::ALL
+        .iter()
+        .flat_map(|&rank| Suit::ALL.iter().map(move |&suit| Card { rank, suit }))
+        .collect()
+}
@@ -0,0 +1,103 @@
+use crate::card::full_deck;
+use crate::rng::Rng;
+use crate::{Card, Error};
+
+/// A deck of cards to deal from, shuffled reproducibly from a seed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deck {
+    /// The cards left, with the top of the deck last.
+    cards: Vec<Card>,
+}
+
+impl Default for Deck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deck {
+    /// A full deck in order, with the twos on top and the aces at the bottom.
+    pub fn new() -> Self {
+        let mut cards = full_deck();
+        cards.reverse();
+        Deck { cards }
+    }
+
+    /// A full deck shuffled from `seed`. The same seed always gives the same
+    /// order.
+    pub fn shuffled(seed: u64) -> Self {
+        let mut deck = Deck::new();
+        deck.shuffle(seed);
+        deck
+    }
+
+    /// Shuffles the cards left in the deck, reproducibly from `seed`.
+    pub fn shuffle(&mut self, seed: u64) {
+        let len = self.cards.len();
+        Rng::new(seed).partial_shuffle(&mut self.cards, len);
+    }
+
+    /// How many cards are left.
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    /// Deals the top card.
+    pub fn deal_one(&mut self) -> Result<Card, Error> {
+        self.cards.pop().ok_or(Error::NotEnoughCards)
+    }
+
+    /// Discards the top card, face down, returning it.
+    pub fn burn(&mut self) -> Result<Card, Error> {
+        self.deal_one()
+    }
+
+    /// Deals `n` cards from the top, in the order they're dealt. Deals
+    /// nothing if there aren't enough.
+    pub fn deal(&mut self, n: usize) -> Result<Vec<Card>, Error> {
+        if n > self.cards.len() {
+            return Err(Error::NotEnoughCards);
+        }
+        let rest = self.cards.len() - n;
+        Ok(self.cards.drain(rest..).rev().collect())
+    }
+
+    /// Deals `cards` cards to each of `players` players, one at a time round
+    /// the table as a dealer would. Deals nothing if there aren't enough.
+    pub fn deal_hands(&mut self, players: usize, cards: usize) -> Result<Vec<Vec<Card>>, Error> {
+        let dealt = self.deal(players.checked_mul(cards).ok_or(Error::NotEnoughCards)?)?;
+        Ok((0..players)
+            .map(|player| {
+                dealt
+                    .iter()
+                    .skip(player)
+                    .step_by(players)
+                    .copied()
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Deals five cards to each of `players` players, written as hand strings
+    /// that `winning_hands` accepts.
+    pub fn deal_hand_strings(&mut self, players: usize) -> Result<Vec<String>, Error> {
+        Ok(self
+            .deal_hands(players, 5)?
+            .iter()
+            .map(|hand| hand_string(hand))
+            .collect())
+    }
+}
+
+/// Writes cards separated by spaces, as in `"4S 5S 7H 8D JC"`.
+pub fn hand_string(cards: &[Card]) -> String {
+    cards
+        .iter()
+        .map(Card::to_string)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -0,0 +1,144 @@
+use std::cmp::Ordering;
+
+use crate::{Hand, HandType, Rank};
+
+/// The rank of the value at `idx`, if there is one and it names a rank.
+fn rank(vals: &[u8], idx: usize) -> Option<Rank> {
+    vals.get(idx).copied().and_then(Rank::from_value)
+}
+
+impl HandType {
+    /// Describes the hand in words, as in "Full house, kings over threes".
+    /// A hand whose values don't name ranks is described by its category
+    /// alone, as in "Full house".
+    pub fn describe(&self) -> String {
+        self.describe_ranks()
+            .unwrap_or_else(|| self.category_name().to_string())
+    }
+
+    fn describe_ranks(&self) -> Option<String> {
+        Some(match self {
+            HandType::Empty => "No hand".to_string(),
+            HandType::High(vals) => format!("High card, {}", rank(vals, 0)?.name()),
+            HandType::OnePair(vals) => format!("Pair of {}", rank(vals, 0)?.plural()),
+            HandType::TwoPairs(vals) => format!(
+                "Two pairs, {} and {}",
+                rank(vals, 0)?.plural(),
+                rank(vals, 2)?.plural()
+            ),
+            HandType::ThreeOfAKind(vals) => {
+                format!("Three of a kind, {}", rank(vals, 0)?.plural())
+            }
+            HandType::Straight(high) => {
+                format!("Straight, {} high", Rank::from_value(*high)?.name())
+            }
+            HandType::Flush(vals) => format!("Flush, {} high", rank(vals, 0)?.name()),
+            HandType::FullHouse(vals) => format!(
+                "Full house, {} over {}",
+                rank(vals, 0)?.plural(),
+                rank(vals, 3)?.plural()
+            ),
+            HandType::FourOfAKind(vals) => {
+                format!("Four of a kind, {}", rank(vals, 0)?.plural())
+            }
+            HandType::StraightFlush(14) => "Royal flush".to_string(),
+            HandType::StraightFlush(high) => {
+                format!("Straight flush, {} high", Rank::from_value(*high)?.name())
+            }
+            HandType::FiveOfAKind(val) => {
+                format!("Five of a kind, {}", Rank::from_value(*val)?.plural())
+            }
+        })
+    }
+
+    /// The hand's category, for describing hands whose values don't name
+    /// ranks.
+    fn category_name(&self) -> &'static str {
+        match self {
+            HandType::Empty => "No hand",
+            HandType::High(_) => "High card",
+            HandType::OnePair(_) => "Pair",
+            HandType::TwoPairs(_) => "Two pairs",
+            HandType::ThreeOfAKind(_) => "Three of a kind",
+            HandType::Straight(_) => "Straight",
+            HandType::Flush(_) => "Flush",
+            HandType::FullHouse(_) => "Full house",
+            HandType::FourOfAKind(_) => "Four of a kind",
+            HandType::StraightFlush(_) => "Straight flush",
+            HandType::FiveOfAKind(_) => "Five of a kind",
+        }
+    }
+
+    /// How many of the leading values are already named by `describe`; any
+    /// values after them are kickers.
+    fn described_len(&self) -> usize {
+        match self {
+            HandType::High(_) | HandType::Flush(_) => 1,
+            HandType::OnePair(_) => 2,
+            HandType::ThreeOfAKind(_) => 3,
+            HandType::TwoPairs(_) | HandType::FourOfAKind(_) => 4,
+            _ => 5,
+        }
+    }
+
+    fn values(&self) -> &[u8] {
+        match self {
+            HandType::High(vals)
+            | HandType::OnePair(vals)
+            | HandType::TwoPairs(vals)
+            | HandType::ThreeOfAKind(vals)
+            | HandType::Flush(vals)
+            | HandType::FullHouse(vals)
+            | HandType::FourOfAKind(vals) => vals,
+            _ => &[],
+        }
+    }
+
+    /// The kickers that decided between two hands of the same description,
+    /// this hand's first, or `None` if their descriptions already differ, the
+    /// hands tie, or the kickers don't name ranks.
+    pub fn deciding_kickers(&self, other: &HandType) -> Option<(Rank, Rank)> {
+        if self.describe() != other.describe() {
+            return None;
+        }
+        self.values()
+            .iter()
+            .zip(other.values())
+            .skip(self.described_len())
+            .find(|(ours, theirs)| ours != theirs)
+            .and_then(|(&ours, &theirs)| Some((Rank::from_value(ours)?, Rank::from_value(theirs)?)))
+    }
+
+    /// Explains in words how this hand fares against another, as in
+    /// "Pair of jacks beats pair of jacks on the kicker, queen against ten".
+    pub fn explain(&self, other: &HandType) -> String {
+        let verb = match self.cmp(other) {
+            Ordering::Greater => "beats",
+            Ordering::Less => "loses to",
+            Ordering::Equal => "ties",
+        };
+        let mut explanation = format!(
+            "{} {} {}",
+            self.describe(),
+            verb,
+            other.describe().to_lowercase()
+        );
+        if let Some((ours, theirs)) = self.deciding_kickers(other) {
+            explanation += &format!(" on the kicker, {} against {}", ours.name(), theirs.name());
+        }
+        explanation
+    }
+}
+
+impl<'a> Hand<'a> {
+    /// Describes the hand in words, as in "Full house, kings over threes".
+    pub fn describe(&self) -> String {
+        self.kind.describe()
+    }
+
+    /// Explains in words how this hand fares against another, naming the
+    /// kicker if one decided it.
+    pub fn explain(&self, other: &Hand) -> String {
+        self.kind.explain(&other.kind)
+    }
+}
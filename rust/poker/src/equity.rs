@@ -0,0 +1,313 @@
+use std::thread;
+
+use crate::card::full_deck;
+use crate::rng::Rng;
+use crate::{check_unique, parse_cards, rank_best, Card, Error, HandRank};
+
+const HOLE_CARDS: usize = 2;
+const BOARD_CARDS: usize = 5;
+
+/// How `holdem_equity` deals out the cards that aren't known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EquityOptions {
+    /// Every possible deal is tried when there are at most this many;
+    /// otherwise deals are sampled at random.
+    pub exhaustive_limit: u64,
+    /// How many deals to sample.
+    pub samples: u64,
+    /// Seeds the sampling, so results can be reproduced. The same seed gives
+    /// the same results whatever the number of threads.
+    pub seed: u64,
+    /// Threads to share the work between.
+    pub threads: usize,
+}
+
+impl Default for EquityOptions {
+    fn default() -> Self {
+        EquityOptions {
+            exhaustive_limit: 1_000_000,
+            samples: 100_000,
+            seed: 0,
+            threads: 1,
+        }
+    }
+}
+
+/// A player's chances at the showdown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Equity {
+    /// The chance of winning the whole pot.
+    pub win: f64,
+    /// The chance of tying with other players for the best hand.
+    pub tie: f64,
+    /// The share of the pot expected on average, counting a tie between
+    /// several players as a split.
+    pub equity: f64,
+}
+
+/// The outcome of `holdem_equity`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Equities {
+    /// Each player's chances, in the order their hole cards were given.
+    pub players: Vec<Equity>,
+    /// How many deals were tried.
+    pub deals: u64,
+    /// Whether every possible deal was tried, making the chances exact.
+    pub exhaustive: bool,
+}
+
+/// Works out each Hold'em player's chances of winning, given the hole cards
+/// and board cards known so far and any dead cards known to be out of play.
+///
+/// Players may have from none to two known hole cards, and the board from
+/// none to five. The missing cards are dealt from the rest of the deck:
+/// every possible way if there are few enough, or otherwise by sampling.
+pub fn holdem_equity(
+    hole_cards: &[&str],
+    board: &str,
+    dead: &str,
+    options: &EquityOptions,
+) -> Result<Equities, Error> {
+    let hands = hole_cards
+        .iter()
+        .map(|hole| parse_cards(hole))
+        .collect::<Result<Vec<Vec<Card>>, Error>>()?;
+    if let Some(hand) = hands.iter().find(|hand| hand.len() > HOLE_CARDS) {
+        return Err(Error::WrongHoleCardCount {
+            expected: HOLE_CARDS,
+            found: hand.len(),
+        });
+    }
+    let board = parse_cards(board)?;
+    if board.len() > BOARD_CARDS {
+        return Err(Error::WrongBoardSize(board.len()));
+    }
+    let dead = parse_cards(dead)?;
+    check_unique(hands.iter().flatten().chain(&board).chain(&dead))?;
+
+    let table = Table::new(hands, board, &dead)?;
+    let deals = table.count_deals();
+    let exhaustive = deals <= u128::from(options.exhaustive_limit);
+    let tally = table.tally(options, exhaustive);
+
+    Ok(Equities {
+        players: (0..table.hands.len())
+            .map(|player| tally.equity(player))
+            .collect(),
+        deals: tally.deals,
+        exhaustive,
+    })
+}
+
+/// The known cards, and the deck the missing ones are dealt from.
+struct Table {
+    hands: Vec<Vec<Card>>,
+    board: Vec<Card>,
+    deck: Vec<Card>,
+    /// How many cards to deal to the board, then to each player in turn.
+    missing: Vec<usize>,
+}
+
+impl Table {
+    fn new(hands: Vec<Vec<Card>>, board: Vec<Card>, dead: &[Card]) -> Result<Self, Error> {
+        let known: Vec<Card> = hands
+            .iter()
+            .flatten()
+            .chain(&board)
+            .chain(dead)
+            .copied()
+            .collect();
+        let deck: Vec<Card> = full_deck()
+            .into_iter()
+            .filter(|card| !known.contains(card))
+            .collect();
+
+        let mut missing = vec![BOARD_CARDS - board.len()];
+        missing.extend(hands.iter().map(|hand| HOLE_CARDS - hand.len()));
+        if missing.iter().sum::<usize>() > deck.len() {
+            return Err(Error::NotEnoughCards);
+        }
+
+        Ok(Table {
+            hands,
+            board,
+            deck,
+            missing,
+        })
+    }
+
+    /// How many different ways the missing cards can be dealt.
+    fn count_deals(&self) -> u128 {
+        let mut left = self.deck.len() as u128;
+        let mut deals: u128 = 1;
+        for &n in &self.missing {
+            let n = n as u128;
+            let ways = (0..n).fold(1u128, |ways, i| ways * (left - i) / (i + 1));
+            deals = deals.saturating_mul(ways);
+            left -= n;
+        }
+        deals
+    }
+
+    fn tally(&self, options: &EquityOptions, exhaustive: bool) -> Tally {
+        let threads = options.threads.max(1) as u64;
+        let tally_share = |share: u64| {
+            let mut tally = Tally::new(self.hands.len());
+            if exhaustive {
+                let mut dealer = Dealer {
+                    table: self,
+                    used: vec![false; self.deck.len()],
+                    dealt: vec![],
+                    threads: threads as usize,
+                    share: share as usize,
+                };
+                dealer.deal_group(0, &mut |deal| tally.add(self.score(deal)));
+            } else {
+                let needed: usize = self.missing.iter().sum();
+                let mut deck = self.deck.clone();
+                for sample in (share..options.samples).step_by(threads as usize) {
+                    deck.copy_from_slice(&self.deck);
+                    Rng::stream(options.seed, sample).partial_shuffle(&mut deck, needed);
+                    tally.add(self.score(&deck[..needed]));
+                }
+            }
+            tally
+        };
+
+        if threads == 1 {
+            return tally_share(0);
+        }
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|share| scope.spawn(move || tally_share(share)))
+                .collect();
+            let mut total = Tally::new(self.hands.len());
+            for handle in handles {
+                total.merge(&handle.join().expect("equity thread panicked"));
+            }
+            total
+        })
+    }
+
+    /// Ranks each player's best hand once `dealt` fills in the missing cards,
+    /// board first and then each player's in turn.
+    fn score(&self, dealt: &[Card]) -> Vec<HandRank> {
+        let (board_extra, mut dealt) = dealt.split_at(self.missing[0]);
+        let mut cards = Vec::with_capacity(HOLE_CARDS + BOARD_CARDS);
+        self.hands
+            .iter()
+            .zip(&self.missing[1..])
+            .map(|(hand, &missing)| {
+                let (hole_extra, rest) = dealt.split_at(missing);
+                dealt = rest;
+                cards.clear();
+                cards.extend(
+                    hand.iter()
+                        .chain(hole_extra)
+                        .chain(&self.board)
+                        .chain(board_extra),
+                );
+                rank_best(&cards).expect("a Hold'em player always has seven cards")
+            })
+            .collect()
+    }
+}
+
+/// Walks through every way of dealing the missing cards, or this thread's
+/// share of them.
+struct Dealer<'a> {
+    table: &'a Table,
+    /// Which cards of the deck have been dealt so far.
+    used: Vec<bool>,
+    dealt: Vec<Card>,
+    /// The threads share out the first card dealt, so that each walks only
+    /// its own branches of the deals.
+    threads: usize,
+    share: usize,
+}
+
+impl Dealer<'_> {
+    /// Deals the missing cards from group `group` on, calling `visit` with
+    /// each complete deal.
+    fn deal_group(&mut self, group: usize, visit: &mut impl FnMut(&[Card])) {
+        match self.table.missing.get(group) {
+            Some(&n) => self.choose(group, n, 0, visit),
+            // with nothing to deal there's a single deal, for the first thread
+            None if self.dealt.is_empty() && self.share != 0 => {}
+            None => visit(&self.dealt),
+        }
+    }
+
+    /// Deals `left` more cards to group `group`, from deck position `from` on
+    /// so that each set of cards is only dealt once.
+    fn choose(&mut self, group: usize, left: usize, from: usize, visit: &mut impl FnMut(&[Card])) {
+        if left == 0 {
+            return self.deal_group(group + 1, visit);
+        }
+        for i in from..self.table.deck.len() {
+            if self.used[i] || (self.dealt.is_empty() && i % self.threads != self.share) {
+                continue;
+            }
+            self.used[i] = true;
+            self.dealt.push(self.table.deck[i]);
+            self.choose(group, left - 1, i + 1, visit);
+            self.dealt.pop();
+            self.used[i] = false;
+        }
+    }
+}
+
+/// Running totals of each player's results. Split pots are counted by how
+/// many ways they were split rather than summed as fractions, so that the
+/// totals don't depend on the order deals are added in.
+struct Tally {
+    /// `pots[player][k]` counts the pots the player shared between `k`
+    /// winners, so `pots[player][1]` counts outright wins.
+    pots: Vec<Vec<u64>>,
+    deals: u64,
+}
+
+impl Tally {
+    fn new(players: usize) -> Self {
+        Tally {
+            pots: vec![vec![0; players + 1]; players],
+            deals: 0,
+        }
+    }
+
+    fn add(&mut self, ranks: Vec<HandRank>) {
+        self.deals += 1;
+        let best = match ranks.iter().max() {
+            Some(&best) => best,
+            None => return,
+        };
+        let winners = ranks.iter().filter(|&&rank| rank == best).count();
+        for (player, &rank) in ranks.iter().enumerate() {
+            if rank == best {
+                self.pots[player][winners] += 1;
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &Tally) {
+        self.deals += other.deals;
+        for (pots, other_pots) in self.pots.iter_mut().zip(&other.pots) {
+            for (count, other_count) in pots.iter_mut().zip(other_pots) {
+                *count += other_count;
+            }
+        }
+    }
+
+    fn equity(&self, player: usize) -> Equity {
+        let total = self.deals.max(1) as f64;
+        let pots = &self.pots[player];
+        Equity {
+            win: pots[1] as f64 / total,
+            tie: pots[2..].iter().sum::<u64>() as f64 / total,
+            equity: (1..pots.len())
+                .map(|winners| pots[winners] as f64 / winners as f64)
+                .sum::<f64>()
+                / total,
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::sync::OnceLock;
+
+use crate::Card;
+
+/// A hand's strength as a single integer. Better hands have higher ranks and
+/// tied hands equal ones, so hands order the same as by `HandType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HandRank(u32);
+
+impl HandRank {
+    pub fn value(self) -> u32 {
+        self.0
+    }
+}
+
+// categories, numbered as the `HandType` variants are declared
+const HIGH: u32 = 1;
+const ONE_PAIR: u32 = 2;
+const TWO_PAIRS: u32 = 3;
+const THREE_OF_A_KIND: u32 = 4;
+const STRAIGHT: u32 = 5;
+const FLUSH: u32 = 6;
+const FULL_HOUSE: u32 = 7;
+const FOUR_OF_A_KIND: u32 = 8;
+const STRAIGHT_FLUSH: u32 = 9;
+const FIVE_OF_A_KIND: u32 = 10;
+
+/// A prime for each rank, so that a product of them identifies which ranks a
+/// hand holds, however they are ordered.
+const PRIMES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+/// The rank bits of the five-high straight, A 2 3 4 5.
+const WHEEL: usize = 0b1_0000_0000_1111;
+
+/// Ranks for every five-card hand, worked out once on first use.
+struct Tables {
+    /// Hands of five different ranks, indexed by their rank bits.
+    distinct: Vec<HandRank>,
+    /// As `distinct`, when the cards are all of one suit.
+    flushes: Vec<HandRank>,
+    /// Hands with a repeated rank, keyed by the product of their ranks'
+    /// primes and sorted by key.
+    repeated: Vec<(u32, HandRank)>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(Tables::build)
+}
+
+/// Packs a category and the ranks that break ties within it, most
+/// significant first, four bits to a rank.
+fn hand_rank(category: u32, ranks: &[u8]) -> HandRank {
+    let mut value = category;
+    for i in 0..5 {
+        value = value << 4 | u32::from(ranks.get(i).copied().unwrap_or(0));
+    }
+    HandRank(value)
+}
+
+impl Tables {
+    fn build() -> Self {
+        let mut tables = Tables {
+            distinct: vec![HandRank(0); 1 << 13],
+            flushes: vec![HandRank(0); 1 << 13],
+            repeated: vec![],
+        };
+
+        for bits in 0..1usize << 13 {
+            if bits.count_ones() != 5 {
+                continue;
+            }
+            let ranks: Vec<u8> = (0..13)
+                .rev()
+                .filter(|r| bits & 1 << r != 0)
+                .map(|r| r + 2)
+                .collect();
+            let straight_high = if bits == WHEEL {
+                Some(5)
+            } else if bits >> bits.trailing_zeros() == 0b11111 {
+                Some(ranks[0])
+            } else {
+                None
+            };
+
+            let (distinct, flush) = match straight_high {
+                Some(high) => (
+                    hand_rank(STRAIGHT, &[high]),
+                    hand_rank(STRAIGHT_FLUSH, &[high]),
+                ),
+                None => (hand_rank(HIGH, &ranks), hand_rank(FLUSH, &ranks)),
+            };
+            tables.distinct[bits] = distinct;
+            tables.flushes[bits] = flush;
+        }
+
+        let mut counts = [0u8; 13];
+        tables.add_repeated(&mut counts, 0, 5);
+        tables.repeated.sort_unstable();
+        tables
+    }
+
+    /// Adds every hand with a repeated rank made by dealing `left` more
+    /// cards, of rank `from` or above, on top of `counts`.
+    fn add_repeated(&mut self, counts: &mut [u8; 13], from: usize, left: u8) {
+        if left == 0 {
+            if counts.iter().all(|&n| n <= 1) {
+                return;
+            }
+            let product = (0..13).map(|r| PRIMES[r].pow(counts[r].into())).product();
+            self.repeated.push((product, repeated_rank(counts)));
+            return;
+        }
+
+        for rank in from..13 {
+            counts[rank] += 1;
+            self.add_repeated(counts, rank, left - 1);
+            counts[rank] -= 1;
+        }
+    }
+}
+
+/// The rank of a hand with a repeated rank, given how many cards of each
+/// rank it holds.
+fn repeated_rank(counts: &[u8; 13]) -> HandRank {
+    // groups of the same rank, largest first, then highest first
+    let mut groups: Vec<(u8, u8)> = (0..13u8)
+        .filter(|&r| counts[usize::from(r)] > 0)
+        .map(|r| (counts[usize::from(r)], r + 2))
+        .collect();
+    groups.sort_unstable_by(|a, b| b.cmp(a));
+
+    let shape: Vec<u8> = groups.iter().map(|&(count, _)| count).collect();
+    let category = match shape.as_slice() {
+        [5] => FIVE_OF_A_KIND,
+        [4, 1] => FOUR_OF_A_KIND,
+        [3, 2] => FULL_HOUSE,
+        [3, 1, 1] => THREE_OF_A_KIND,
+        [2, 2, 1] => TWO_PAIRS,
+        _ => ONE_PAIR,
+    };
+    let ranks: Vec<u8> = groups.iter().map(|&(_, rank)| rank).collect();
+    hand_rank(category, &ranks)
+}
+
+/// Ranks a hand of five different cards without allocating.
+pub fn rank_five(cards: &[Card; 5]) -> HandRank {
+    let tables = tables();
+    let mut bits: usize = 0;
+    let mut product = 1;
+    let mut suits = 0b1111;
+    for card in cards {
+        let rank = usize::from(card.rank.value() - 2);
+        bits |= 1 << rank;
+        product *= PRIMES[rank];
+        suits &= 1 << card.suit as u8;
+    }
+
+    if bits.count_ones() == 5 {
+        if suits != 0 {
+            tables.flushes[bits]
+        } else {
+            tables.distinct[bits]
+        }
+    } else {
+        let idx = tables
+            .repeated
+            .binary_search_by_key(&product, |&(key, _)| key)
+            .expect("every product of five ranks is in the table");
+        tables.repeated[idx].1
+    }
+}
+
+/// Ranks the best five-card hand among `cards`, or `None` if there are
+/// fewer than five of them.
+pub fn rank_best(cards: &[Card]) -> Option<HandRank> {
+    let n = cards.len();
+    let mut best = None;
+    for a in 0..n {
+        for b in a + 1..n {
+            for c in b + 1..n {
+                for d in c + 1..n {
+                    for e in d + 1..n {
+                        let rank = rank_five(&[cards[a], cards[b], cards[c], cards[d], cards[e]]);
+                        best = best.max(Some(rank));
+                    }
+                }
+            }
+        }
+    }
+    best
+}
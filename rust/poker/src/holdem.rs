@@ -0,0 +1,69 @@
+use crate::{check_unique, combinations, parse_cards, Card, Error, Hand, HandType};
+
+/// The best five-card hand a player can make at a showdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BestHand {
+    /// The player's position in the list of hole cards.
+    pub player: usize,
+    pub kind: HandType,
+    /// The five cards making the hand, taken from the hole cards and board.
+    pub cards: Vec<Card>,
+}
+
+/// The best five-card hand among `cards`, or `None` if there are fewer than
+/// five of them.
+pub fn best_five(cards: &[Card]) -> Option<(HandType, Vec<Card>)> {
+    combinations(cards, 5)
+        .into_iter()
+        .map(|five| (Hand::process_hand(&five), five))
+        .max_by(|(kind1, _), (kind2, _)| kind1.cmp(kind2))
+}
+
+/// Given the community cards on the board and each player's two hole cards,
+/// returns the players whose best five of those cards win, along with those
+/// five cards. Tied players all win.
+///
+/// The board can hold from three to five cards, so showdowns can be scored
+/// after the flop and turn as well as the river. Fails if any cards are
+/// malformed, or if a card appears more than once.
+pub fn holdem_winners(board: &str, hole_cards: &[&str]) -> Result<Vec<BestHand>, Error> {
+    let board = parse_cards(board)?;
+    if !(3..=5).contains(&board.len()) {
+        return Err(Error::WrongBoardSize(board.len()));
+    }
+
+    let hole_cards = hole_cards
+        .iter()
+        .map(|hole| parse_cards(hole))
+        .collect::<Result<Vec<Vec<Card>>, Error>>()?;
+    if let Some(hole) = hole_cards.iter().find(|hole| hole.len() != 2) {
+        return Err(Error::WrongHoleCardCount {
+            expected: 2,
+            found: hole.len(),
+        });
+    }
+    check_unique(board.iter().chain(hole_cards.iter().flatten()))?;
+
+    let hands: Vec<BestHand> = hole_cards
+        .iter()
+        .enumerate()
+        .filter_map(|(player, hole)| {
+            let cards: Vec<Card> = hole.iter().chain(&board).copied().collect();
+            let (kind, cards) = best_five(&cards)?;
+            Some(BestHand {
+                player,
+                kind,
+                cards,
+            })
+        })
+        .collect();
+
+    let best_kind = match hands.iter().map(|hand| &hand.kind).max() {
+        Some(kind) => kind.clone(),
+        None => return Ok(vec![]),
+    };
+    Ok(hands
+        .into_iter()
+        .filter(|hand| hand.kind == best_kind)
+        .collect())
+}
@@ -1,7 +1,74 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+mod card;
+mod deck;
+mod describe;
+mod equity;
+mod eval;
+mod holdem;
+mod omaha;
+mod rng;
+mod rules;
+
+pub use card::{Card, Rank, Suit};
+pub use deck::{hand_string, Deck};
+pub use equity::{holdem_equity, Equities, Equity, EquityOptions};
+pub use eval::{rank_best, rank_five, HandRank};
+pub use holdem::{best_five, holdem_winners, BestHand};
+pub use omaha::{omaha_hi_lo_winners, omaha_winners, BestLow, Showdown};
+pub use rules::{winning_hands_with, Ranking, Rules};
+
+/// Why hands couldn't be read or compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    InvalidRank(String),
+    InvalidSuit(String),
+    /// A hand didn't have exactly five cards.
+    WrongCardCount(usize),
+    /// A player was dealt the wrong number of hole cards.
+    WrongHoleCardCount {
+        expected: usize,
+        found: usize,
+    },
+    /// The board had the wrong number of cards.
+    WrongBoardSize(usize),
+    /// The same card was dealt more than once.
+    DuplicateCard(Card),
+    /// There weren't enough cards left in the deck to deal.
+    NotEnoughCards,
+    /// A card isn't in the deck the rules play with.
+    CardNotInDeck(Card),
+    /// More jokers were dealt than the deck holds.
+    TooManyJokers(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidRank(rank) => {
+                write!(f, "invalid rank `{}`, expected 2-10, J, Q, K or A", rank)
+            }
+            Error::InvalidSuit(suit) => write!(f, "invalid suit `{}`, expected C, D, H or S", suit),
+            Error::WrongCardCount(n) => write!(f, "a hand needs 5 cards, not {}", n),
+            Error::WrongHoleCardCount { expected, found } => {
+                write!(f, "a player needs {} hole cards, not {}", expected, found)
+            }
+            Error::WrongBoardSize(n) => write!(f, "the board can't hold {} cards", n),
+            Error::DuplicateCard(card) => write!(f, "{} was dealt more than once", card),
+            Error::NotEnoughCards => write!(f, "not enough cards are left in the deck"),
+            Error::CardNotInDeck(card) => write!(f, "{} isn't in this deck", card),
+            Error::TooManyJokers(n) => {
+                write!(f, "{} jokers were dealt, more than the deck holds", n)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
 
-#[derive(Debug, Ord, PartialEq, PartialOrd, Eq)]
+#[derive(Debug, Clone, Ord, PartialEq, PartialOrd, Eq)]
 pub enum HandType {
     Empty,
     High(Vec<u8>),
@@ -16,10 +83,21 @@ pub enum HandType {
     FiveOfAKind(u8),
 }
 
+impl HandType {
+    /// Classifies a hand of five cards. Fails if there aren't exactly five.
+    pub fn from_cards(cards: &[Card]) -> Result<Self, Error> {
+        if cards.len() != 5 {
+            return Err(Error::WrongCardCount(cards.len()));
+        }
+        Ok(Hand::process_hand(cards))
+    }
+}
+
 #[derive(Debug, Eq)]
 pub struct Hand<'a> {
     kind: HandType,
     handstr: &'a str,
+    cards: Vec<Card>,
 }
 
 impl<'a> Ord for Hand<'a> {
@@ -47,24 +125,6 @@ impl<'a> Default for Hand<'a> {
 }
 
 impl<'a> Hand<'a> {
-    fn parse_card_value(card: &str) -> u8 {
-        match card.chars().next().unwrap() {
-            i if char::is_numeric(i) => match i.to_digit(10).unwrap() {
-                n if n == 1 => 10,
-                n => n as u8,
-            },
-            'J' => 11,
-            'Q' => 12,
-            'K' => 13,
-            'A' => 14,
-            _ => 0,
-        }
-    }
-
-    fn parse_card_suit(card: &'a str) -> char {
-        card.chars().rev().next().unwrap()
-    }
-
     fn count_pairs_and_triplets(vals: &[u8]) -> (u8, u8, u8) {
         let mut counts: HashMap<u8, u8> = HashMap::new();
 
@@ -108,12 +168,10 @@ impl<'a> Hand<'a> {
             sorted_vec.append(&mut vec![v; c]);
         }
 
-        println!("{:?}", sorted_vec);
-
         sorted_vec
     }
 
-    fn evaluate_hand(mut vals: Vec<u8>, mut suits: Vec<char>) -> HandType {
+    fn evaluate_hand(mut vals: Vec<u8>, mut suits: Vec<Suit>) -> HandType {
         const STRAIGHTS: &[u8] = &[14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 14, 5, 4, 3, 2];
 
         suits.sort_unstable();
@@ -122,13 +180,9 @@ impl<'a> Hand<'a> {
 
         vals.sort_unstable();
         vals.reverse();
-        // println!("{:?}", vals);
-        let maxval = *vals.get(0).unwrap();
-
-        // reduced is a vector of unique values in the hand, in descending order
-        let reduced = Hand::sort_hand(&vals);
+        let maxval = vals[0];
 
-        if reduced.len() == 1 {
+        if vals.iter().all(|&val| val == maxval) {
             return HandType::FiveOfAKind(maxval);
         }
 
@@ -150,10 +204,24 @@ impl<'a> Hand<'a> {
 
         // if it's not a straight, and it's a flush, it's just a flush
         if is_flush {
-            return HandType::Flush(reduced);
+            return HandType::Flush(Hand::sort_hand(&vals));
+        }
+
+        Hand::group_hand(&vals)
+    }
+
+    /// Classifies a hand by its repeated ranks alone, ignoring straights and
+    /// flushes.
+    fn group_hand(vals: &[u8]) -> HandType {
+        // reduced holds the values grouped by how often they appear, largest
+        // groups and then highest values first
+        let reduced = Hand::sort_hand(vals);
+
+        if reduced.iter().all(|&val| val == reduced[0]) {
+            return HandType::FiveOfAKind(reduced[0]);
         }
 
-        match Hand::count_pairs_and_triplets(&vals) {
+        match Hand::count_pairs_and_triplets(vals) {
             (_, _, 1) => HandType::FourOfAKind(reduced),
             (1, 0, _) => HandType::OnePair(reduced),
             (1, 1, _) => HandType::FullHouse(reduced),
@@ -163,36 +231,44 @@ impl<'a> Hand<'a> {
         }
     }
 
-    fn process_hand(handlist: Vec<&str>) -> HandType {
-        let vals: Vec<u8> = handlist
-            .iter()
-            .map(|card| Hand::parse_card_value(card))
-            .collect();
-        // println!("vals of {:?} are {:?}", handlist, vals);
-
-        let suits: Vec<char> = handlist
-            .iter()
-            .map(|card| Hand::parse_card_suit(card))
-            .collect();
-        // println!("suits of {:?} are {:?}", handlist, suits);
+    fn process_hand(cards: &[Card]) -> HandType {
+        let vals: Vec<u8> = cards.iter().map(|card| card.rank.value()).collect();
+        let suits: Vec<Suit> = cards.iter().map(|card| card.suit).collect();
 
         Hand::evaluate_hand(vals, suits)
     }
 
-    pub fn from_handstr(handstr: &'a str) -> Self {
-        let mut hand = Hand::new();
-        hand.handstr = handstr;
+    /// Reads a hand of five cards separated by whitespace, such as
+    /// `"4S 5S 7H 8D JC"`.
+    ///
+    /// Cards aren't checked for duplicates here, so hands dealt from
+    /// several decks can still be compared.
+    pub fn from_handstr(handstr: &'a str) -> Result<Self, Error> {
+        let cards = parse_cards(handstr)?;
+        if cards.len() != 5 {
+            return Err(Error::WrongCardCount(cards.len()));
+        }
+
+        Ok(Hand {
+            kind: Hand::process_hand(&cards),
+            handstr,
+            cards,
+        })
+    }
 
-        let handlist: Vec<&str> = handstr.split_whitespace().collect();
-        hand.kind = Hand::process_hand(handlist);
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
 
-        hand
+    pub fn kind(&self) -> &HandType {
+        &self.kind
     }
 
     pub fn new() -> Self {
         Hand {
             kind: HandType::Empty,
             handstr: "",
+            cards: vec![],
         }
     }
 }
@@ -201,21 +277,57 @@ impl<'a> Hand<'a> {
 ///
 /// Note the type signature: this function should return _the same_ reference to
 /// the winning hand(s) as were passed in, not reconstructed strings which happen to be equal.
-pub fn winning_hands<'a>(hands: &[&'a str]) -> Option<Vec<&'a str>> {
-    let mut hands = hands
+///
+/// Fails if any hand is malformed, or if a card appears more than once.
+pub fn winning_hands<'a>(hands: &[&'a str]) -> Result<Vec<&'a str>, Error> {
+    let hands = hands
         .iter()
         .map(|h| Hand::from_handstr(h))
-        .collect::<Vec<Hand>>();
-    hands.sort_unstable();
-
-    println!("{:?}", hands);
-    let best_hand = hands.iter().max().unwrap();
-
-    Some(
-        hands
-            .iter()
-            .filter(|&h| h == best_hand)
-            .map(|h| h.handstr)
-            .collect::<Vec<&'a str>>(),
-    )
+        .collect::<Result<Vec<Hand>, Error>>()?;
+    check_unique(hands.iter().flat_map(|h| h.cards()))?;
+
+    let best_hand = match hands.iter().max() {
+        Some(best_hand) => best_hand,
+        None => return Ok(vec![]),
+    };
+
+    Ok(hands
+        .iter()
+        .filter(|&h| h == best_hand)
+        .map(|h| h.handstr)
+        .collect::<Vec<&'a str>>())
+}
+
+/// Reads cards separated by whitespace.
+fn parse_cards(cards: &str) -> Result<Vec<Card>, Error> {
+    cards.split_whitespace().map(str::parse).collect()
+}
+
+/// Every way of choosing `k` of `cards`, keeping them in order.
+fn combinations(cards: &[Card], k: usize) -> Vec<Vec<Card>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    if cards.len() < k {
+        return vec![];
+    }
+
+    let (&first, rest) = cards.split_first().unwrap();
+    let mut with_first = combinations(rest, k - 1);
+    for combination in &mut with_first {
+        combination.insert(0, first);
+    }
+    with_first.extend(combinations(rest, k));
+    with_first
+}
+
+/// Checks that no card appears twice, as it couldn't from a single deck.
+fn check_unique<'c>(cards: impl IntoIterator<Item = &'c Card>) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    for &card in cards {
+        if !seen.insert(card) {
+            return Err(Error::DuplicateCard(card));
+        }
+    }
+    Ok(())
 }
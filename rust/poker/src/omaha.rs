@@ -0,0 +1,176 @@
+use std::cmp::Reverse;
+
+use crate::{check_unique, combinations, parse_cards, BestHand, Card, Error, Hand, HandType};
+
+const HOLE_CARDS: usize = 4;
+
+/// The best qualifying low hand a player can make in Omaha Hi-Lo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BestLow {
+    /// The player's position in the list of hole cards.
+    pub player: usize,
+    /// The five cards making the hand, highest first, counting aces low.
+    pub cards: Vec<Card>,
+}
+
+/// Who wins an Omaha showdown, and how the pot is split between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Showdown {
+    /// The players with the best high hand, who share the pot, or half of it
+    /// if a low hand qualifies.
+    pub high: Vec<BestHand>,
+    /// The players with the best qualifying low hand, who share the other
+    /// half of the pot. Empty if nobody has a low hand, or the game has no
+    /// low half.
+    pub low: Vec<BestLow>,
+    /// Each player's share of the pot, in the order their hole cards were
+    /// given.
+    pub shares: Vec<f64>,
+}
+
+/// Given the board and each player's four hole cards, returns who wins an
+/// Omaha showdown. Each player's hand is made of exactly two of their hole
+/// cards and exactly three board cards.
+///
+/// The board can hold from three to five cards. Fails if any cards are
+/// malformed, or if a card appears more than once.
+pub fn omaha_winners(board: &str, hole_cards: &[&str]) -> Result<Showdown, Error> {
+    let (board, hole_cards) = parse_deal(board, hole_cards)?;
+    let high = high_winners(&board, &hole_cards);
+    Ok(split_pot(hole_cards.len(), high, vec![]))
+}
+
+/// As `omaha_winners`, but for Omaha Hi-Lo, where the best low hand wins
+/// half the pot. A low hand qualifies with five different ranks of eight or
+/// below, aces counting low; straights and flushes don't count against it.
+/// If no low hand qualifies, the high hand wins the whole pot.
+pub fn omaha_hi_lo_winners(board: &str, hole_cards: &[&str]) -> Result<Showdown, Error> {
+    let (board, hole_cards) = parse_deal(board, hole_cards)?;
+    let high = high_winners(&board, &hole_cards);
+    let low = low_winners(&board, &hole_cards);
+    Ok(split_pot(hole_cards.len(), high, low))
+}
+
+fn parse_deal(board: &str, hole_cards: &[&str]) -> Result<(Vec<Card>, Vec<Vec<Card>>), Error> {
+    let board = parse_cards(board)?;
+    if !(3..=5).contains(&board.len()) {
+        return Err(Error::WrongBoardSize(board.len()));
+    }
+
+    let hole_cards = hole_cards
+        .iter()
+        .map(|hole| parse_cards(hole))
+        .collect::<Result<Vec<Vec<Card>>, Error>>()?;
+    if let Some(hole) = hole_cards.iter().find(|hole| hole.len() != HOLE_CARDS) {
+        return Err(Error::WrongHoleCardCount {
+            expected: HOLE_CARDS,
+            found: hole.len(),
+        });
+    }
+    check_unique(board.iter().chain(hole_cards.iter().flatten()))?;
+    Ok((board, hole_cards))
+}
+
+/// Every five-card hand a player may make: two hole cards and three from
+/// the board.
+fn omaha_hands(board: &[Card], hole: &[Card]) -> Vec<Vec<Card>> {
+    let boards = combinations(board, 3);
+    combinations(hole, 2)
+        .into_iter()
+        .flat_map(|two| {
+            boards
+                .iter()
+                .map(move |three| two.iter().chain(three).copied().collect())
+        })
+        .collect()
+}
+
+fn high_winners(board: &[Card], hole_cards: &[Vec<Card>]) -> Vec<BestHand> {
+    let hands: Vec<BestHand> = hole_cards
+        .iter()
+        .enumerate()
+        .filter_map(|(player, hole)| {
+            let (kind, cards) = omaha_hands(board, hole)
+                .into_iter()
+                .map(|five| (Hand::process_hand(&five), five))
+                .max_by(|(kind1, _), (kind2, _)| kind1.cmp(kind2))?;
+            Some(BestHand {
+                player,
+                kind,
+                cards,
+            })
+        })
+        .collect();
+
+    let best_kind: HandType = match hands.iter().map(|hand| &hand.kind).max() {
+        Some(kind) => kind.clone(),
+        None => return vec![],
+    };
+    hands
+        .into_iter()
+        .filter(|hand| hand.kind == best_kind)
+        .collect()
+}
+
+/// The card's value when aces count low.
+fn low_value(card: &Card) -> u8 {
+    match card.rank.value() {
+        14 => 1,
+        val => val,
+    }
+}
+
+/// The hand's values with aces low, highest first, if it qualifies as an
+/// eight-or-better low.
+fn low_values(cards: &[Card]) -> Option<Vec<u8>> {
+    let mut vals: Vec<u8> = cards.iter().map(low_value).collect();
+    vals.sort_unstable_by(|a, b| b.cmp(a));
+    let distinct = vals.windows(2).all(|pair| pair[0] != pair[1]);
+    if distinct && vals[0] <= 8 {
+        Some(vals)
+    } else {
+        None
+    }
+}
+
+fn low_winners(board: &[Card], hole_cards: &[Vec<Card>]) -> Vec<BestLow> {
+    // lower values are better lows, so each player keeps their smallest
+    let lows: Vec<(Vec<u8>, BestLow)> = hole_cards
+        .iter()
+        .enumerate()
+        .filter_map(|(player, hole)| {
+            omaha_hands(board, hole)
+                .into_iter()
+                .filter_map(|mut five| {
+                    let vals = low_values(&five)?;
+                    five.sort_unstable_by_key(|card| Reverse(low_value(card)));
+                    Some((vals, five))
+                })
+                .min_by(|(vals1, _), (vals2, _)| vals1.cmp(vals2))
+                .map(|(vals, cards)| (vals, BestLow { player, cards }))
+        })
+        .collect();
+
+    let best = match lows.iter().map(|(vals, _)| vals).min() {
+        Some(best) => best.clone(),
+        None => return vec![],
+    };
+    lows.into_iter()
+        .filter(|(vals, _)| *vals == best)
+        .map(|(_, low)| low)
+        .collect()
+}
+
+/// Splits the pot evenly between the high winners, or in half between the
+/// high and low winners if anyone has a low hand.
+fn split_pot(players: usize, high: Vec<BestHand>, low: Vec<BestLow>) -> Showdown {
+    let mut shares = vec![0.0; players];
+    let high_pot = if low.is_empty() { 1.0 } else { 0.5 };
+    for hand in &high {
+        shares[hand.player] += high_pot / high.len() as f64;
+    }
+    for hand in &low {
+        shares[hand.player] += 0.5 / low.len() as f64;
+    }
+    Showdown { high, low, shares }
+}
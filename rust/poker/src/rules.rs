@@ -0,0 +1,232 @@
+use std::cmp::Reverse;
+use std::iter;
+
+use crate::{check_unique, Card, Error, Hand, HandType, Rank, Suit};
+
+/// How a game orders its hands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ranking {
+    /// The usual order, where the highest hand wins.
+    High,
+    /// Lowball where the lowest hand wins, aces are low, and straights and
+    /// flushes don't count, so A 2 3 4 5 is the best hand.
+    AceToFive,
+    /// Lowball where the lowest hand wins, aces are high, and straights and
+    /// flushes count against a hand, so 2 3 4 5 7 is the best hand.
+    DeuceToSeven,
+    /// A 36-card deck without the twos to fives, where a flush beats a full
+    /// house and A 6 7 8 9 is the lowest straight.
+    ShortDeck,
+}
+
+/// The rules of a poker variant, for `winning_hands_with`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    pub ranking: Ranking,
+    /// Ranks whose cards are wild, as twos are in deuces wild.
+    pub wild_ranks: Vec<Rank>,
+    /// How many jokers the deck holds. Jokers are wild, and are written `JK`.
+    pub jokers: usize,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            ranking: Ranking::High,
+            wild_ranks: vec![],
+            jokers: 0,
+        }
+    }
+}
+
+/// How good a hand is under some rules. Better hands are greater.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Strength {
+    /// The hand's category in the rules' order, then the hand itself.
+    High(u8, HandType),
+    Low(Reverse<(u8, HandType)>),
+}
+
+/// A hand's cards, with its jokers set apart.
+struct WildHand<'a> {
+    handstr: &'a str,
+    cards: Vec<Card>,
+    jokers: usize,
+}
+
+impl Rules {
+    fn ranks(&self) -> &'static [Rank] {
+        match self.ranking {
+            Ranking::ShortDeck => &Rank::ALL[4..],
+            _ => &Rank::ALL,
+        }
+    }
+
+    fn parse_hand<'a>(&self, handstr: &'a str) -> Result<WildHand<'a>, Error> {
+        let mut hand = WildHand {
+            handstr,
+            cards: vec![],
+            jokers: 0,
+        };
+        for token in handstr.split_whitespace() {
+            if token == "JK" {
+                hand.jokers += 1;
+                continue;
+            }
+            let card: Card = token.parse()?;
+            if !self.ranks().contains(&card.rank) {
+                return Err(Error::CardNotInDeck(card));
+            }
+            hand.cards.push(card);
+        }
+        let count = hand.cards.len() + hand.jokers;
+        if count != 5 {
+            return Err(Error::WrongCardCount(count));
+        }
+        Ok(hand)
+    }
+
+    /// The strongest the hand can be made by choosing what its wild cards
+    /// stand for.
+    fn best_strength(&self, hand: &WildHand) -> Strength {
+        let natural: Vec<Card> = hand
+            .cards
+            .iter()
+            .filter(|card| !self.wild_ranks.contains(&card.rank))
+            .copied()
+            .collect();
+        let mut vals: Vec<u8> = natural.iter().map(|card| card.rank.value()).collect();
+        let wild = hand.cards.len() + hand.jokers - natural.len();
+        let mut best = None;
+        self.choose_wild(&mut vals, wild, 0, &natural, &mut best);
+        best.expect("a hand always has five cards")
+    }
+
+    /// Tries every choice of ranks for the `left` wild cards still to be
+    /// given one, from rank index `from` up so each choice is tried once.
+    fn choose_wild(
+        &self,
+        vals: &mut Vec<u8>,
+        left: usize,
+        from: usize,
+        natural: &[Card],
+        best: &mut Option<Strength>,
+    ) {
+        if left == 0 {
+            // wild cards either follow the natural cards' suit, making a
+            // flush if they share one, or take turns through the other suits
+            // so that they can't make one
+            let suit = natural.first().map_or(Suit::Spades, |card| card.suit);
+            let naturals = natural.iter().map(|card| card.suit);
+            let others = Suit::ALL.iter().copied().filter(|&s| s != suit).cycle();
+            let follow: Vec<Suit> = naturals
+                .clone()
+                .chain(iter::repeat(suit))
+                .take(vals.len())
+                .collect();
+            let mixed: Vec<Suit> = naturals.chain(others).take(vals.len()).collect();
+            for suits in [follow, mixed] {
+                *best = best.take().max(Some(self.strength(vals, suits)));
+            }
+            return;
+        }
+
+        for (idx, rank) in self.ranks().iter().enumerate().skip(from) {
+            vals.push(rank.value());
+            self.choose_wild(vals, left - 1, idx, natural, best);
+            vals.pop();
+        }
+    }
+
+    fn strength(&self, vals: &[u8], suits: Vec<Suit>) -> Strength {
+        let mut sorted = vals.to_vec();
+        sorted.sort_unstable_by(|a, b| b.cmp(a));
+
+        match self.ranking {
+            Ranking::High => {
+                let kind = Hand::evaluate_hand(sorted, suits);
+                Strength::High(category(&kind), kind)
+            }
+            Ranking::ShortDeck => {
+                let kind = match Hand::evaluate_hand(sorted.clone(), suits) {
+                    HandType::High(_) if sorted == [14, 9, 8, 7, 6] => HandType::Straight(9),
+                    HandType::Flush(_) if sorted == [14, 9, 8, 7, 6] => HandType::StraightFlush(9),
+                    kind => kind,
+                };
+                let category = match category(&kind) {
+                    FLUSH => FULL_HOUSE,
+                    FULL_HOUSE => FLUSH,
+                    category => category,
+                };
+                Strength::High(category, kind)
+            }
+            Ranking::AceToFive => {
+                let aces_low: Vec<u8> = sorted
+                    .iter()
+                    .map(|&val| if val == 14 { 1 } else { val })
+                    .collect();
+                let kind = Hand::group_hand(&aces_low);
+                Strength::Low(Reverse((category(&kind), kind)))
+            }
+            Ranking::DeuceToSeven => {
+                let kind = match Hand::evaluate_hand(sorted.clone(), suits) {
+                    // A 2 3 4 5 isn't a straight with aces high
+                    HandType::Straight(5) => HandType::High(sorted),
+                    HandType::StraightFlush(5) => HandType::Flush(sorted),
+                    kind => kind,
+                };
+                Strength::Low(Reverse((category(&kind), kind)))
+            }
+        }
+    }
+}
+
+const FLUSH: u8 = 6;
+const FULL_HOUSE: u8 = 7;
+
+/// The hand's category, numbered as the `HandType` variants are declared.
+fn category(kind: &HandType) -> u8 {
+    match kind {
+        HandType::Empty => 0,
+        HandType::High(_) => 1,
+        HandType::OnePair(_) => 2,
+        HandType::TwoPairs(_) => 3,
+        HandType::ThreeOfAKind(_) => 4,
+        HandType::Straight(_) => 5,
+        HandType::Flush(_) => FLUSH,
+        HandType::FullHouse(_) => FULL_HOUSE,
+        HandType::FourOfAKind(_) => 8,
+        HandType::StraightFlush(_) => 9,
+        HandType::FiveOfAKind(_) => 10,
+    }
+}
+
+/// As `winning_hands`, but under the given rules: wild cards stand for
+/// whatever makes their hand best, and hands are ordered by the rules'
+/// ranking.
+///
+/// Fails if any hand is malformed, holds a card missing from the rules' deck,
+/// or if a card appears more than once.
+pub fn winning_hands_with<'a>(hands: &[&'a str], rules: &Rules) -> Result<Vec<&'a str>, Error> {
+    let hands = hands
+        .iter()
+        .map(|hand| rules.parse_hand(hand))
+        .collect::<Result<Vec<WildHand>, Error>>()?;
+    check_unique(hands.iter().flat_map(|hand| &hand.cards))?;
+    let jokers: usize = hands.iter().map(|hand| hand.jokers).sum();
+    if jokers > rules.jokers {
+        return Err(Error::TooManyJokers(jokers));
+    }
+
+    let strengths: Vec<Strength> = hands.iter().map(|hand| rules.best_strength(hand)).collect();
+    let best = match strengths.iter().max() {
+        Some(best) => best,
+        None => return Ok(vec![]),
+    };
+    Ok(hands
+        .iter()
+        .zip(&strengths)
+        .filter(|(_, strength)| *strength == best)
+        .map(|(hand, _)| hand.handstr)
+        .collect())
+}
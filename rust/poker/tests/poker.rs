@@ -1,5 +1,9 @@
-use poker::winning_hands;
-use std::collections::HashSet;
+use poker::{
+    best_five, hand_string, holdem_equity, holdem_winners, omaha_hi_lo_winners, omaha_winners,
+    rank_best, rank_five, winning_hands, winning_hands_with, Card, Deck, EquityOptions, Error,
+    Hand, HandType, Rank, Ranking, Rules, Suit,
+};
+use std::collections::{HashMap, HashSet};
 
 fn hs_from<'a>(input: &[&'a str]) -> HashSet<&'a str> {
     let mut hs = HashSet::new();
@@ -14,13 +18,31 @@ fn hs_from<'a>(input: &[&'a str]) -> HashSet<&'a str> {
 ///
 /// Note that the output can be in any order. Here, we use a HashSet to
 /// abstract away the order of outputs.
-fn test<'a, 'b>(input: &[&'a str], expected: &[&'b str]) {
+fn test(input: &[&str], expected: &[&str]) {
     assert_eq!(
         hs_from(&winning_hands(input).expect("This test should produce Some value",)),
         hs_from(expected)
     )
 }
 
+/// Like `test`, but for hands dealt from several decks, which `winning_hands`
+/// rejects for sharing cards. The hands are compared directly instead.
+fn test_multiple_decks(input: &[&str], expected: &[&str]) {
+    let hands: Vec<Hand> = input
+        .iter()
+        .map(|h| Hand::from_handstr(h).expect("This test should have valid hands"))
+        .collect();
+    let best = hands.iter().max().unwrap();
+    let winners: Vec<&str> = input
+        .iter()
+        .zip(&hands)
+        .filter(|&(_, h)| h == best)
+        .map(|(&h, _)| h)
+        .collect();
+    assert_eq!(hs_from(&winners), hs_from(expected));
+    assert!(matches!(winning_hands(input), Err(Error::DuplicateCard(_))));
+}
+
 #[test]
 fn test_single_hand_always_wins() {
     test(&["4S 5S 7H 8D JC"], &["4S 5S 7H 8D JC"])
@@ -86,7 +108,7 @@ fn test_two_pairs_second_pair_cascade() {
 fn test_two_pairs_last_card_cascade() {
     // both hands have two identically ranked pairs,
     // tie goes to remaining card (kicker)
-    test(&["JD QH JS 8D QC", "JS QS JC 2D QD"], &["JD QH JS 8D QC"])
+    test(&["JD QH JS 8D QC", "JH QS JC 2D QD"], &["JD QH JS 8D QC"])
 }
 
 #[test]
@@ -104,7 +126,7 @@ fn test_three_of_a_kind_ranks() {
 fn test_three_of_a_kind_cascade_ranks() {
     // with multiple decks, two players can have same three of a kind,
     // ties go to highest remaining cards
-    test(&["4S AH AS 7C AD", "4S AH AS 8C AD"], &["4S AH AS 8C AD"])
+    test_multiple_decks(&["4S AH AS 7C AD", "4S AH AS 8C AD"], &["4S AH AS 8C AD"])
 }
 
 #[test]
@@ -161,7 +183,7 @@ fn test_full_house_ranks() {
 #[test]
 fn test_full_house_cascade() {
     // with multiple decks, both hands have a full house with the same triplet, tie goes to the pair
-    test(&["5H 5S 5D 9S 9D", "5H 5S 5D 8S 8D"], &["5H 5S 5D 9S 9D"])
+    test_multiple_decks(&["5H 5S 5D 9S 9D", "5H 5S 5D 8S 8D"], &["5H 5S 5D 9S 9D"])
 }
 
 #[test]
@@ -178,7 +200,7 @@ fn test_four_of_a_kind_ranks() {
 #[test]
 fn test_four_of_a_kind_cascade() {
     // with multiple decks, both hands with identical four of a kind, tie determined by kicker
-    test(&["3S 3H 2S 3D 3C", "3S 3H 4S 3D 3C"], &["3S 3H 4S 3D 3C"])
+    test_multiple_decks(&["3S 3H 2S 3D 3C", "3S 3H 4S 3D 3C"], &["3S 3H 4S 3D 3C"])
 }
 
 #[test]
@@ -191,3 +213,797 @@ fn test_straight_flush_ranks() {
     // both hands have straight flush, tie goes to highest-ranked card
     test(&["4H 6H 7H 8H 5H", "5S 7S 8S 9S 6S"], &["5S 7S 8S 9S 6S"])
 }
+
+#[test]
+fn test_cards_parse_and_display() {
+    let card: Card = "10H".parse().unwrap();
+    assert_eq!(
+        Card {
+            rank: Rank::Ten,
+            suit: Suit::Hearts
+        },
+        card
+    );
+    assert_eq!("10H", card.to_string());
+    assert_eq!(Ok(Rank::Ace), "A".parse());
+    assert_eq!(Ok(Suit::Clubs), "C".parse());
+}
+
+#[test]
+fn test_invalid_cards_are_described() {
+    assert_eq!(
+        Err(Error::InvalidRank("1".to_string())),
+        "1H".parse::<Card>()
+    );
+    assert_eq!(
+        Err(Error::InvalidSuit("X".to_string())),
+        "4X".parse::<Card>()
+    );
+    assert_eq!(Err(Error::InvalidRank("".to_string())), "S".parse::<Card>());
+    assert_eq!(Err(Error::InvalidSuit("".to_string())), "".parse::<Card>());
+    assert_eq!(
+        "invalid suit `X`, expected C, D, H or S",
+        Error::InvalidSuit("X".to_string()).to_string()
+    );
+}
+
+#[test]
+fn test_malformed_hands_are_errors() {
+    assert_eq!(
+        Err(Error::InvalidRank("1".to_string())),
+        winning_hands(&["4S 5S 7H 8D JC", "2S 3S 4S 5S 1S"])
+    );
+    assert_eq!(
+        Err(Error::WrongCardCount(4)),
+        winning_hands(&["4S 5S 7H 8D JC", "2S 3S 4S 5S"])
+    );
+    assert_eq!(Err(Error::WrongCardCount(0)), winning_hands(&[""]));
+}
+
+#[test]
+fn test_duplicate_cards_are_rejected() {
+    assert_eq!(
+        Err(Error::DuplicateCard("JC".parse().unwrap())),
+        winning_hands(&["4S 5S 7H 8D JC", "2S 3S 6S JC 9H"])
+    );
+    assert_eq!(
+        Err(Error::DuplicateCard("4S".parse().unwrap())),
+        winning_hands(&["4S 5S 7H 8D 4S"])
+    );
+}
+
+#[test]
+fn test_no_hands_no_winners() {
+    assert_eq!(Ok(vec![]), winning_hands(&[]));
+}
+
+fn cards(cards: &str) -> Vec<Card> {
+    cards
+        .split_whitespace()
+        .map(|c| c.parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_best_five_of_seven() {
+    assert_eq!(
+        Some((HandType::Straight(9), cards("5D 6C 7S 8H 9C"))),
+        best_five(&cards("2H 5D 6C 7S 8H 9C KD"))
+    );
+    assert_eq!(None, best_five(&cards("2H 5D 6C 7S")));
+}
+
+#[test]
+fn test_holdem_best_hand_uses_board_and_hole_cards() {
+    let winners = holdem_winners("2H 7H 9H JC QD", &["AH 3H", "JD JS"]).unwrap();
+    assert_eq!(1, winners.len());
+    assert_eq!(0, winners[0].player);
+    assert_eq!(HandType::Flush(vec![14, 9, 7, 3, 2]), winners[0].kind);
+    assert_eq!(cards("AH 3H 2H 7H 9H"), winners[0].cards);
+}
+
+#[test]
+fn test_holdem_board_can_play() {
+    // both players' best hand is the straight on the board
+    let winners = holdem_winners("10C JD QS KH AC", &["2H 3D", "4S 4C"]).unwrap();
+    let players: Vec<usize> = winners.iter().map(|w| w.player).collect();
+    assert_eq!(vec![0, 1], players);
+    assert!(winners.iter().all(|w| w.cards == cards("10C JD QS KH AC")));
+}
+
+#[test]
+fn test_holdem_kicker_from_hole_cards() {
+    let winners = holdem_winners("KH KD 7C 4S 2D", &["AS 9C", "QS JC", "AD 8H"]).unwrap();
+    let players: Vec<usize> = winners.iter().map(|w| w.player).collect();
+    assert_eq!(vec![0], players);
+    assert_eq!(HandType::OnePair(vec![13, 13, 14, 9, 7]), winners[0].kind);
+}
+
+#[test]
+fn test_holdem_after_the_flop() {
+    let winners = holdem_winners("5S 5C 9D", &["9S 9H", "5H 2C"]).unwrap();
+    assert_eq!(0, winners[0].player);
+    assert_eq!(HandType::FullHouse(vec![9, 9, 9, 5, 5]), winners[0].kind);
+}
+
+#[test]
+fn test_holdem_rejects_bad_deals() {
+    assert_eq!(
+        Err(Error::WrongHoleCardCount {
+            expected: 2,
+            found: 3
+        }),
+        holdem_winners("2H 7H 9H JC QD", &["AH 3H 4C"])
+    );
+    assert_eq!(
+        Err(Error::WrongBoardSize(2)),
+        holdem_winners("2H 7H", &["AH 3H"])
+    );
+    assert_eq!(
+        Err(Error::DuplicateCard("7H".parse().unwrap())),
+        holdem_winners("2H 7H 9H JC QD", &["AH 3H", "7H 8D"])
+    );
+    assert_eq!(
+        Err(Error::InvalidSuit("X".to_string())),
+        holdem_winners("2H 7H 9H JC QD", &["AX 3H"])
+    );
+}
+
+fn deck() -> Vec<Card> {
+    Rank::ALL
+        .iter()
+        .flat_map(|&rank| Suit::ALL.iter().map(move |&suit| Card { rank, suit }))
+        .collect()
+}
+
+/// Calls `f` with every five-card hand from one deck.
+fn for_every_hand(mut f: impl FnMut([Card; 5])) {
+    let deck = deck();
+    for a in 0..52 {
+        for b in a + 1..52 {
+            for c in b + 1..52 {
+                for d in c + 1..52 {
+                    for e in d + 1..52 {
+                        f([deck[a], deck[b], deck[c], deck[d], deck[e]]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_rank_five_counts_every_hand() {
+    let mut categories: HashMap<u32, usize> = HashMap::new();
+    let mut ranks = HashSet::new();
+    for_every_hand(|hand| {
+        let rank = rank_five(&hand);
+        *categories.entry(rank.value() >> 20).or_insert(0) += 1;
+        ranks.insert(rank);
+    });
+
+    let expected: HashMap<u32, usize> = [
+        (1, 1_302_540),
+        (2, 1_098_240),
+        (3, 123_552),
+        (4, 54_912),
+        (5, 10_200),
+        (6, 5_108),
+        (7, 3_744),
+        (8, 624),
+        (9, 40),
+    ]
+    .iter()
+    .copied()
+    .collect();
+    assert_eq!(expected, categories);
+    assert_eq!(7_462, ranks.len());
+}
+
+#[test]
+fn test_rank_five_orders_hands_like_hand_type() {
+    let mut sample = vec![];
+    let mut n = 0;
+    for_every_hand(|hand| {
+        n += 1;
+        if n % 499 == 0 {
+            sample.push((HandType::from_cards(&hand).unwrap(), rank_five(&hand)));
+        }
+    });
+
+    sample.sort();
+    for pair in sample.windows(2) {
+        let (kind1, rank1) = &pair[0];
+        let (kind2, rank2) = &pair[1];
+        assert_eq!(
+            kind1.cmp(kind2),
+            rank1.cmp(rank2),
+            "{:?} vs {:?}",
+            kind1,
+            kind2
+        );
+    }
+}
+
+#[test]
+fn test_rank_five_wheel_is_the_lowest_straight() {
+    let wheel = rank_five(&[
+        "AH".parse().unwrap(),
+        "2C".parse().unwrap(),
+        "3D".parse().unwrap(),
+        "4S".parse().unwrap(),
+        "5H".parse().unwrap(),
+    ]);
+    let six_high = rank_five(&[
+        "2H".parse().unwrap(),
+        "3C".parse().unwrap(),
+        "4D".parse().unwrap(),
+        "5S".parse().unwrap(),
+        "6H".parse().unwrap(),
+    ]);
+    assert!(wheel < six_high);
+}
+
+#[test]
+fn test_rank_best_of_seven() {
+    let seven = cards("2H 5D 6C 7S 8H 9C KD");
+    let (_, five) = best_five(&seven).unwrap();
+    let five = [five[0], five[1], five[2], five[3], five[4]];
+    assert_eq!(Some(rank_five(&five)), rank_best(&seven));
+    assert_eq!(None, rank_best(&cards("2H 5D 6C 7S")));
+}
+
+#[test]
+fn test_equity_on_the_river_is_certain() {
+    let equities = holdem_equity(
+        &["AS AH", "KS KH"],
+        "2C 7D 9H JC 3S",
+        "",
+        &EquityOptions::default(),
+    )
+    .expect("a valid deal");
+    assert!(equities.exhaustive);
+    assert_eq!(equities.deals, 1);
+    assert_eq!(equities.players[0].win, 1.0);
+    assert_eq!(equities.players[1].equity, 0.0);
+}
+
+#[test]
+fn test_equity_on_the_turn_tries_every_river() {
+    let equities = holdem_equity(
+        &["AS AH", "KS KH"],
+        "2C 7D 9H JC",
+        "",
+        &EquityOptions::default(),
+    )
+    .expect("a valid deal");
+    assert!(equities.exhaustive);
+    assert_eq!(equities.deals, 44);
+    // only the two remaining kings save the kings
+    assert_eq!(equities.players[1].win, 2.0 / 44.0);
+    assert_eq!(equities.players[0].win, 42.0 / 44.0);
+
+    let with_dead = holdem_equity(
+        &["AS AH", "KS KH"],
+        "2C 7D 9H JC",
+        "KC",
+        &EquityOptions::default(),
+    )
+    .expect("a valid deal");
+    assert_eq!(with_dead.deals, 43);
+    assert_eq!(with_dead.players[1].win, 1.0 / 43.0);
+}
+
+#[test]
+fn test_equity_counts_split_pots() {
+    // a 2 or a 3 makes trips, and an ace or a nine a straight for both
+    let equities = holdem_equity(
+        &["2S 2H", "3S 3H"],
+        "10C JD QH KC",
+        "",
+        &EquityOptions::default(),
+    )
+    .expect("a valid deal");
+    let [twos, threes] = [equities.players[0], equities.players[1]];
+    assert_eq!(twos.win, 2.0 / 44.0);
+    assert_eq!(twos.tie, 8.0 / 44.0);
+    assert_eq!(threes.tie, twos.tie);
+    assert_eq!(threes.win, 34.0 / 44.0);
+    assert_eq!(threes.equity, threes.win + threes.tie / 2.0);
+}
+
+#[test]
+fn test_equity_samples_when_there_are_too_many_deals() {
+    let options = EquityOptions {
+        samples: 20_000,
+        seed: 7,
+        ..EquityOptions::default()
+    };
+    let equities = holdem_equity(&["AS AH", "KS KH"], "", "", &options).expect("a valid deal");
+    assert!(!equities.exhaustive);
+    assert_eq!(equities.deals, 20_000);
+    assert!((equities.players[0].equity - 0.82).abs() < 0.02);
+    let total: f64 = equities.players.iter().map(|player| player.equity).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_equity_sampling_is_reproducible() {
+    let options = EquityOptions {
+        samples: 2_000,
+        seed: 42,
+        ..EquityOptions::default()
+    };
+    let hands = ["AS KS", "QH QD", ""];
+    let once = holdem_equity(&hands, "", "", &options).expect("a valid deal");
+    let again = holdem_equity(&hands, "", "", &options).expect("a valid deal");
+    let threaded = holdem_equity(
+        &hands,
+        "",
+        "",
+        &EquityOptions {
+            threads: 4,
+            ..options
+        },
+    )
+    .expect("a valid deal");
+    assert_eq!(once, again);
+    assert_eq!(once, threaded);
+}
+
+#[test]
+fn test_equity_threads_share_every_deal() {
+    let hands = ["AS AH", "KS"];
+    let options = EquityOptions::default();
+    let single = holdem_equity(&hands, "2C 7D 9H", "", &options).expect("a valid deal");
+    let threaded = holdem_equity(
+        &hands,
+        "2C 7D 9H",
+        "",
+        &EquityOptions {
+            threads: 3,
+            ..options
+        },
+    )
+    .expect("a valid deal");
+    assert!(single.exhaustive);
+    assert_eq!(single.deals, 46 * 45 / 2 * 44);
+    assert_eq!(single, threaded);
+}
+
+#[test]
+fn test_equity_threads_count_a_complete_deal_once() {
+    let options = EquityOptions {
+        threads: 4,
+        ..EquityOptions::default()
+    };
+    let equities =
+        holdem_equity(&["AS AH", "KS KH"], "2C 7D 9H 3S 4D", "", &options).expect("a valid deal");
+    assert_eq!(equities.deals, 1);
+    assert_eq!(equities.players[0].win, 1.0);
+}
+
+#[test]
+fn test_equity_rejects_bad_deals() {
+    let options = EquityOptions::default();
+    assert_eq!(
+        holdem_equity(&["AS AH KS"], "", "", &options),
+        Err(Error::WrongHoleCardCount {
+            expected: 2,
+            found: 3
+        })
+    );
+    assert_eq!(
+        holdem_equity(&["AS AH"], "2C 3C 4C 5C 6C 7C", "", &options),
+        Err(Error::WrongBoardSize(6))
+    );
+    assert_eq!(
+        holdem_equity(&["AS AH"], "2C 3C 4C", "AH", &options),
+        Err(Error::DuplicateCard("AH".parse().unwrap()))
+    );
+    let crowd = vec![""; 24];
+    assert_eq!(
+        holdem_equity(&crowd, "", "", &options),
+        Err(Error::NotEnoughCards)
+    );
+}
+
+fn describe(hand: &str) -> String {
+    Hand::from_handstr(hand).expect("a valid hand").describe()
+}
+
+fn explain(hand: &str, other: &str) -> String {
+    let hand = Hand::from_handstr(hand).expect("a valid hand");
+    let other = Hand::from_handstr(other).expect("a valid hand");
+    hand.explain(&other)
+}
+
+#[test]
+fn test_describe_every_category() {
+    assert_eq!(describe("2S 5H 9D JC KS"), "High card, king");
+    assert_eq!(describe("JS JH 9D 4C 2S"), "Pair of jacks");
+    assert_eq!(describe("AS AH 8D 8C 5S"), "Two pairs, aces and eights");
+    assert_eq!(describe("7S 7H 7D KC 2S"), "Three of a kind, sevens");
+    assert_eq!(describe("AS 2H 3D 4C 5S"), "Straight, five high");
+    assert_eq!(describe("2H 6H 9H JH KH"), "Flush, king high");
+    assert_eq!(describe("KS KH KD 3C 3S"), "Full house, kings over threes");
+    assert_eq!(describe("6S 6H 6D 6C QS"), "Four of a kind, sixes");
+    assert_eq!(describe("5D 6D 7D 8D 9D"), "Straight flush, nine high");
+    assert_eq!(describe("10C JC QC KC AC"), "Royal flush");
+}
+
+#[test]
+fn test_describe_hand_types_without_ranks() {
+    assert_eq!(HandType::High(vec![]).describe(), "High card");
+    assert_eq!(HandType::TwoPairs(vec![9, 9]).describe(), "Two pairs");
+    assert_eq!(HandType::Straight(0).describe(), "Straight");
+    assert_eq!(HandType::Empty.describe(), "No hand");
+    assert_eq!(
+        HandType::OnePair(vec![9, 9, 20]).deciding_kickers(&HandType::OnePair(vec![9, 9, 5])),
+        None
+    );
+    assert_eq!(
+        HandType::High(vec![]).explain(&HandType::High(vec![])),
+        "High card ties high card"
+    );
+}
+
+#[test]
+fn test_explain_names_the_deciding_kicker() {
+    assert_eq!(
+        explain("JS JH QD 4C 2S", "JD JC 10D 4H 2H"),
+        "Pair of jacks beats pair of jacks on the kicker, queen against ten"
+    );
+    assert_eq!(
+        explain("AS AH 8D 8C 5S", "AD AC 8H 8S 9S"),
+        "Two pairs, aces and eights loses to two pairs, aces and eights on the kicker, five against nine"
+    );
+    assert_eq!(
+        explain("KS KH KD 3C 3S", "AS AH 8D 8C 5D"),
+        "Full house, kings over threes beats two pairs, aces and eights"
+    );
+    assert_eq!(
+        explain("2H 6H 9H JH KH", "3D 6D 9D JD KD"),
+        "Flush, king high loses to flush, king high on the kicker, two against three"
+    );
+    assert_eq!(
+        explain("2S 5H 9D JC KS", "2D 5C 9H JD KC"),
+        "High card, king ties high card, king"
+    );
+}
+
+#[test]
+fn test_hand_type_needs_five_cards() {
+    assert_eq!(HandType::from_cards(&[]), Err(Error::WrongCardCount(0)));
+    assert_eq!(
+        HandType::from_cards(&cards("KS KD KH")),
+        Err(Error::WrongCardCount(3))
+    );
+    assert_eq!(
+        HandType::from_cards(&cards("KS KD KH 2C 2D 3S")),
+        Err(Error::WrongCardCount(6))
+    );
+}
+
+#[test]
+fn test_deciding_kickers() {
+    let kind = |hand| HandType::from_cards(&cards(hand)).unwrap();
+    assert_eq!(
+        kind("JS JH QD 4C 2S").deciding_kickers(&kind("JD JC QS 5C 2H")),
+        Some((Rank::Four, Rank::Five))
+    );
+    assert_eq!(
+        kind("JS JH QD 4C 2S").deciding_kickers(&kind("QH QC 3D 4H 2H")),
+        None
+    );
+}
+
+fn test_with(rules: &Rules, input: &[&str], expected: &[&str]) {
+    assert_eq!(
+        hs_from(&winning_hands_with(input, rules).expect("This test should produce Some value",)),
+        hs_from(expected)
+    )
+}
+
+fn deuces_wild() -> Rules {
+    Rules {
+        wild_ranks: vec![Rank::Two],
+        ..Rules::default()
+    }
+}
+
+#[test]
+fn test_default_rules_match_winning_hands() {
+    let hands = ["4D 5S 6S 8D 3C", "2S 4C 7S 9H 10H", "3S 4S 5D 6H JH"];
+    test_with(&Rules::default(), &hands, &["3S 4S 5D 6H JH"]);
+}
+
+#[test]
+fn test_wild_cards_make_five_of_a_kind() {
+    test_with(
+        &deuces_wild(),
+        &["AS AH AD AC 2C", "10H JH QH KH 2H"],
+        &["AS AH AD AC 2C"],
+    );
+}
+
+#[test]
+fn test_wild_cards_complete_straights_and_flushes() {
+    test_with(
+        &deuces_wild(),
+        &["9S 10D JC KH 2S", "3H 3D 3C 8S 8C"],
+        &["3H 3D 3C 8S 8C"],
+    );
+    test_with(
+        &deuces_wild(),
+        &["9S 10D JC KH 2S", "3H 3D 8C 8S 4C"],
+        &["9S 10D JC KH 2S"],
+    );
+    test_with(
+        &deuces_wild(),
+        &["4H 7H 9H KH 2S", "AS AD AC 5C 5D"],
+        &["AS AD AC 5C 5D"],
+    );
+}
+
+#[test]
+fn test_jokers_are_wild() {
+    let rules = Rules {
+        jokers: 2,
+        ..Rules::default()
+    };
+    test_with(
+        &rules,
+        &["KS KH KD JK JK", "AS AH AD AC 3C"],
+        &["KS KH KD JK JK"],
+    );
+    assert_eq!(
+        winning_hands_with(&["KS KH KD JK JK", "AS AH AD JK 3C"], &rules),
+        Err(Error::TooManyJokers(3))
+    );
+    assert_eq!(
+        winning_hands_with(&["KS KH KD QS JK"], &Rules::default()),
+        Err(Error::TooManyJokers(1))
+    );
+}
+
+#[test]
+fn test_ace_to_five_lowball() {
+    let rules = Rules {
+        ranking: Ranking::AceToFive,
+        ..Rules::default()
+    };
+    // the wheel is the best hand, and its straight and flush don't count
+    test_with(
+        &rules,
+        &["AH 2H 3H 4H 5H", "2S 3S 4D 6C 7D"],
+        &["AH 2H 3H 4H 5H"],
+    );
+    test_with(
+        &rules,
+        &["2S 3S 4D 6C 8D", "2D 3D 4C 6S 7H"],
+        &["2D 3D 4C 6S 7H"],
+    );
+    // any unpaired hand beats a pair
+    test_with(
+        &rules,
+        &["2S 2H 3D 4C 5D", "9S 10H JD QC KD"],
+        &["9S 10H JD QC KD"],
+    );
+}
+
+#[test]
+fn test_deuce_to_seven_lowball() {
+    let rules = Rules {
+        ranking: Ranking::DeuceToSeven,
+        ..Rules::default()
+    };
+    test_with(
+        &rules,
+        &["2S 3S 4D 5C 7D", "AS 2D 3C 4H 5H"],
+        &["2S 3S 4D 5C 7D"],
+    );
+    // straights and flushes count against a hand
+    test_with(
+        &rules,
+        &["3S 4S 5D 6C 7D", "2D 3C 4H 5H 8S"],
+        &["2D 3C 4H 5H 8S"],
+    );
+    test_with(
+        &rules,
+        &["2H 3H 4H 5H 7H", "2S 3D 4C 5D 9S"],
+        &["2S 3D 4C 5D 9S"],
+    );
+    // wild cards alone can still make an off-suit seven low
+    let wild = Rules {
+        ranking: Ranking::DeuceToSeven,
+        wild_ranks: vec![Rank::Two],
+        jokers: 1,
+    };
+    test_with(
+        &wild,
+        &["2S 2H 2D 2C JK", "9S 8H 6D 4C 3D"],
+        &["2S 2H 2D 2C JK"],
+    );
+    let jokers = Rules {
+        ranking: Ranking::DeuceToSeven,
+        wild_ranks: vec![],
+        jokers: 5,
+    };
+    test_with(
+        &jokers,
+        &["JK JK JK JK JK", "8S 7H 5D 4C 3D"],
+        &["JK JK JK JK JK"],
+    );
+}
+
+#[test]
+fn test_short_deck() {
+    let rules = Rules {
+        ranking: Ranking::ShortDeck,
+        ..Rules::default()
+    };
+    test_with(
+        &rules,
+        &["6H 8H 10H QH AH", "KS KH KD 9C 9D"],
+        &["6H 8H 10H QH AH"],
+    );
+    test_with(
+        &rules,
+        &["AS 6D 7C 8H 9H", "KS KH KD 9C 10D"],
+        &["AS 6D 7C 8H 9H"],
+    );
+    assert_eq!(
+        winning_hands_with(&["AS 6D 7C 8H 2H"], &rules),
+        Err(Error::CardNotInDeck("2H".parse().unwrap()))
+    );
+}
+
+#[test]
+fn test_deck_shuffles_reproducibly() {
+    assert_eq!(Deck::shuffled(3), Deck::shuffled(3));
+    assert_ne!(Deck::shuffled(3), Deck::shuffled(4));
+    assert_ne!(Deck::shuffled(3), Deck::new());
+
+    let mut deck = Deck::shuffled(3);
+    let cards: HashSet<Card> = deck.deal(52).expect("a full deck").into_iter().collect();
+    assert_eq!(cards.len(), 52);
+    assert!(deck.is_empty());
+}
+
+#[test]
+fn test_deck_deals_from_the_top() {
+    let mut deck = Deck::new();
+    assert_eq!(deck.len(), 52);
+    assert_eq!(deck.burn(), Ok("2C".parse().unwrap()));
+    assert_eq!(deck.deal(2), Ok(cards("2D 2H")));
+    assert_eq!(deck.deal_one(), Ok("2S".parse().unwrap()));
+    assert_eq!(
+        deck.deal_hands(2, 2),
+        Ok(vec![cards("3C 3H"), cards("3D 3S")])
+    );
+    assert_eq!(deck.len(), 44);
+}
+
+#[test]
+fn test_deck_runs_out() {
+    let mut deck = Deck::shuffled(9);
+    assert_eq!(deck.deal_hand_strings(11), Err(Error::NotEnoughCards));
+    assert_eq!(deck.deal_hands(usize::MAX, 2), Err(Error::NotEnoughCards));
+    assert_eq!(deck.len(), 52);
+    assert_eq!(deck.deal_hand_strings(10).map(|hands| hands.len()), Ok(10));
+    deck.burn().expect("two cards left");
+    deck.burn().expect("one card left");
+    assert_eq!(deck.burn(), Err(Error::NotEnoughCards));
+}
+
+#[test]
+fn test_dealt_hands_agree_with_the_evaluator() {
+    for seed in 0..500 {
+        let mut deck = Deck::shuffled(seed);
+        let hands = deck.deal_hand_strings(6).expect("enough cards for six");
+        let hands: Vec<&str> = hands.iter().map(String::as_str).collect();
+        let winners = winning_hands(&hands).expect("hands from one deck are valid");
+
+        let ranks: Vec<_> = hands
+            .iter()
+            .map(|hand| rank_best(&cards(hand)).unwrap())
+            .collect();
+        let best = ranks.iter().max().unwrap();
+        let expected: Vec<&str> = hands
+            .iter()
+            .zip(&ranks)
+            .filter(|(_, rank)| *rank == best)
+            .map(|(hand, _)| *hand)
+            .collect();
+        assert_eq!(winners, expected, "seed {}", seed);
+    }
+}
+
+#[test]
+fn test_hand_string_round_trips() {
+    let hand = "4S 5S 7H 8D JC";
+    assert_eq!(hand_string(&cards(hand)), hand);
+}
+
+fn players<T>(hands: &[T], player: impl Fn(&T) -> usize) -> Vec<usize> {
+    hands.iter().map(player).collect()
+}
+
+#[test]
+fn test_omaha_needs_two_hole_cards_for_a_flush() {
+    // four hearts on the board are no use with only one in the hand
+    let showdown = omaha_winners("2H 7H 9H JH 3C", &["AH KS 5D 4C", "QS QD 8C 8D"]).unwrap();
+    assert_eq!(players(&showdown.high, |hand| hand.player), vec![1]);
+    assert_eq!(
+        showdown.high[0].kind,
+        HandType::from_cards(&cards("QS QD 9H JH 7H")).unwrap()
+    );
+    assert_eq!(showdown.shares, vec![0.0, 1.0]);
+}
+
+#[test]
+fn test_omaha_uses_only_three_board_cards() {
+    // a single ace in the hand can't play with the board's four of a kind
+    let showdown = omaha_winners("9S 9H 9D 9C 2C", &["AS 3D 4H 6C", "KS KD 5H 6D"]).unwrap();
+    assert_eq!(players(&showdown.high, |hand| hand.player), vec![1]);
+    assert_eq!(
+        showdown.high[0].kind,
+        HandType::from_cards(&cards("KS KD 9S 9H 9D")).unwrap()
+    );
+}
+
+#[test]
+fn test_omaha_splits_ties() {
+    let showdown = omaha_winners("10S JH QD 2C 2D", &["AS KS 3H 4H", "AD KD 5C 6C"]).unwrap();
+    assert_eq!(players(&showdown.high, |hand| hand.player), vec![0, 1]);
+    assert_eq!(showdown.shares, vec![0.5, 0.5]);
+    assert!(showdown.low.is_empty());
+}
+
+#[test]
+fn test_omaha_hi_lo_splits_the_pot() {
+    let showdown = omaha_hi_lo_winners("2S 5H 7D KC KD", &["KS KH 9C 10C", "AS 3D JC QC"]).unwrap();
+    assert_eq!(players(&showdown.high, |hand| hand.player), vec![0]);
+    assert_eq!(players(&showdown.low, |hand| hand.player), vec![1]);
+    assert_eq!(showdown.low[0].cards, cards("7D 5H 3D 2S AS"));
+    assert_eq!(showdown.shares, vec![0.5, 0.5]);
+}
+
+#[test]
+fn test_omaha_hi_lo_without_a_low_scoops() {
+    let showdown =
+        omaha_hi_lo_winners("2S 9H 10D KC KD", &["KS KH 9C 10C", "AS 3D JC QC"]).unwrap();
+    assert!(showdown.low.is_empty());
+    assert_eq!(showdown.shares, vec![1.0, 0.0]);
+}
+
+#[test]
+fn test_omaha_hi_lo_quarters_a_shared_low() {
+    let showdown = omaha_hi_lo_winners(
+        "2S 5H 7D KC KD",
+        &["KS KH 9C 10C", "AS 3D JC QC", "AD 3C 10H 10S"],
+    )
+    .unwrap();
+    assert_eq!(players(&showdown.low, |hand| hand.player), vec![1, 2]);
+    assert_eq!(showdown.shares, vec![0.5, 0.25, 0.25]);
+}
+
+#[test]
+fn test_omaha_rejects_bad_deals() {
+    assert_eq!(
+        omaha_winners("2S 5H 7D", &["KS KH 9C"]),
+        Err(Error::WrongHoleCardCount {
+            expected: 4,
+            found: 3
+        })
+    );
+    assert_eq!(
+        omaha_hi_lo_winners("2S 5H", &["KS KH 9C 10C"]),
+        Err(Error::WrongBoardSize(2))
+    );
+    assert_eq!(
+        omaha_winners("2S 5H 7D", &["KS KH 9C 2S"]),
+        Err(Error::DuplicateCard("2S".parse().unwrap()))
+    );
+}